@@ -1,3 +1,4 @@
+use crate::primitives::Hash;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -8,4 +9,24 @@ pub enum BChainError {
     UserNotFound(String),
     #[error("User: {0} failed to produce bloch with err:{1}")]
     BlockProductionFailure(String, String),
+    #[error("Block at height: {0} not found")]
+    BlockNotFound(usize),
+    #[error("Contract: {0} not found")]
+    ContractNotFound(String),
+    #[error("Contract: {0} has nothing stored under key: {1}")]
+    StorageNotFound(String, String),
+    #[error("Query of contract: {0} failed with err:{1}")]
+    QueryFailure(String, String),
+    #[error("Transaction: {1} of block at height: {0} not found")]
+    TransactionNotFound(usize, usize),
+    #[error("Transaction: {1} not included in block at height: {0}")]
+    TransactionNotIncluded(usize, Hash),
+    #[error("No snapshot available")]
+    SnapshotNotAvailable,
+    #[error("Invalid snapshot: {0}")]
+    InvalidSnapshot(String),
+    #[error("State at height: {0} was pruned, only archive nodes keep it")]
+    StatePruned(usize),
+    #[error("Replay of block at height: {0} failed with err:{1}")]
+    ReplayFailure(usize, String),
 }
@@ -1,14 +1,22 @@
+mod tx_index;
+
 use crate::config::static_config;
-use crate::primitives::{Block, Transaction, TransactionType};
+use crate::primitives::{Block, Bloom, Hash, Receipt, Transaction, TransactionType, TxLocation};
+use tx_index::TxIndex;
 
 #[derive(Debug)]
 pub struct Chain {
+    /// Height of the first block kept, 0 unless the chain started from a snapshot
+    base: usize,
     chain: Vec<Block>,
+    receipts: Vec<Vec<Receipt>>,
+    tx_index: TxIndex,
 }
 
 impl Chain {
-    pub fn new(state_root: String) -> Self {
+    pub fn new(state_root: Hash) -> Self {
         let genesis_block = Block::new(
+            static_config::GENESIS_TIMESTAMP,
             vec![Transaction::new(
                 static_config::GENESIS.to_string(),
                 TransactionType::Transfer {
@@ -18,28 +26,87 @@ impl Chain {
                 },
                 0.0,
             )],
-            "0".to_string(),
+            Hash::default(),
             static_config::GENESIS.to_string(),
             state_root,
-        );
-        Self {
+            Bloom::default(),
+        )
+        .expect("genesis block has a canonical encoding");
+        let mut chain = Self {
+            base: 0,
             chain: vec![genesis_block],
-        }
+            receipts: vec![vec![Receipt::default()]],
+            tx_index: TxIndex::default(),
+        };
+        chain.rebuild_index();
+        chain
+    }
+
+    /// Chain ending with `blocks`, the last of which is at `height` and had
+    /// its state restored from a snapshot. Their receipts are not known.
+    pub fn from_snapshot(height: usize, blocks: Vec<Block>) -> Self {
+        let mut chain = Self {
+            base: height + 1 - blocks.len(),
+            receipts: vec![vec![]; blocks.len()],
+            chain: blocks,
+            tx_index: TxIndex::default(),
+        };
+        chain.rebuild_index();
+        chain
     }
 
     pub fn len(&self) -> usize {
-        self.chain.len()
+        self.base + self.chain.len()
+    }
+
+    /// Height of the first block kept
+    pub fn first_height(&self) -> usize {
+        self.base
     }
 
     pub fn get_block_by_idx(&self, idx: usize) -> Option<&Block> {
-        self.chain.get(idx)
+        self.chain.get(idx.checked_sub(self.base)?)
     }
 
     pub fn get_last_block(&self) -> Option<&Block> {
         self.chain.last()
     }
 
-    pub fn push(&mut self, block: Block) {
+    pub fn get_receipts_by_idx(&self, idx: usize) -> Option<&Vec<Receipt>> {
+        self.receipts.get(idx.checked_sub(self.base)?)
+    }
+
+    /// Copy of the blocks below height `len`
+    pub fn truncated(&self, len: usize) -> Self {
+        let mut chain = Self {
+            base: self.base,
+            chain: self.chain[..len - self.base].to_vec(),
+            receipts: self.receipts[..len - self.base].to_vec(),
+            tx_index: TxIndex::default(),
+        };
+        chain.rebuild_index();
+        chain
+    }
+
+    pub fn push(&mut self, block: Block, receipts: Vec<Receipt>) {
+        self.tx_index.add_block(self.len(), &block, &receipts);
         self.chain.push(block);
+        self.receipts.push(receipts);
+    }
+
+    /// Recomputes the transaction indexes from the blocks, needed whenever
+    /// blocks are replaced rather than appended
+    pub fn rebuild_index(&mut self) {
+        self.tx_index = TxIndex::build(self.base, self.chain.iter().zip(&self.receipts));
+    }
+
+    /// Every position holding a transaction with `tx_hash`
+    pub fn get_tx_locations(&self, tx_hash: &Hash) -> &[TxLocation] {
+        self.tx_index.locations(tx_hash)
+    }
+
+    /// Transactions sent by, sent to or otherwise touching `account`, oldest first
+    pub fn get_account_history(&self, account: &str) -> &[TxLocation] {
+        self.tx_index.account_history(account)
     }
 }
@@ -0,0 +1,93 @@
+use crate::primitives::{Block, Hash, Receipt, TransactionType, TxLocation};
+use std::collections::HashMap;
+
+/// Secondary indexes over the transactions of the chain, derived from the
+/// blocks and their receipts only.
+#[derive(Debug, Default)]
+pub struct TxIndex {
+    /// Transactions carry no nonce, so identical ones share a hash
+    by_hash: HashMap<Hash, Vec<TxLocation>>,
+    /// Transactions touching an account, in chain order
+    by_account: HashMap<String, Vec<TxLocation>>,
+}
+
+impl TxIndex {
+    /// Indexes `blocks`, the first of which is at height `base`
+    pub fn build<'a>(
+        base: usize,
+        blocks: impl Iterator<Item = (&'a Block, &'a Vec<Receipt>)>,
+    ) -> Self {
+        let mut index = Self::default();
+        for (offset, (block, receipts)) in blocks.enumerate() {
+            index.add_block(base + offset, block, receipts);
+        }
+        index
+    }
+
+    pub fn add_block(&mut self, height: usize, block: &Block, receipts: &[Receipt]) {
+        for (index, tx) in block.transactions.iter().enumerate() {
+            let location = TxLocation { height, index };
+            // Blocks only hold transactions that have a hash
+            if let Ok(hash) = tx.hash() {
+                self.by_hash.entry(hash).or_default().push(location);
+            }
+            let mut accounts = vec![tx.sender.as_str()];
+            touched_accounts(&tx.tx_type, receipts.get(index), &mut accounts);
+            accounts.sort_unstable();
+            accounts.dedup();
+            for account in accounts {
+                self.by_account
+                    .entry(account.to_string())
+                    .or_default()
+                    .push(location);
+            }
+        }
+    }
+
+    pub fn locations(&self, tx_hash: &Hash) -> &[TxLocation] {
+        self.by_hash.get(tx_hash).map_or(&[], Vec::as_slice)
+    }
+
+    pub fn account_history(&self, account: &str) -> &[TxLocation] {
+        self.by_account.get(account).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Accounts an operation touches besides its sender, `receipt` being its
+/// outcome. Contracts it ran may have paid accounts it does not name.
+fn touched_accounts<'a>(
+    tx_type: &'a TransactionType,
+    receipt: Option<&'a Receipt>,
+    accounts: &mut Vec<&'a str>,
+) {
+    accounts.extend(
+        receipt
+            .iter()
+            .flat_map(|r| r.paid_accounts.iter().map(String::as_str)),
+    );
+    match tx_type {
+        TransactionType::Transfer {
+            sender, receiver, ..
+        } => accounts.extend([sender.as_str(), receiver.as_str()]),
+        TransactionType::Stake { user, .. } | TransactionType::Unstake { user, .. } => {
+            accounts.push(user)
+        }
+        TransactionType::DeployContract { .. } => {
+            // The address is known only once the deployment ran
+            accounts.extend(receipt.and_then(|r| r.contract_address.as_deref()))
+        }
+        TransactionType::CallContract {
+            contract_address, ..
+        }
+        | TransactionType::UpgradeContract {
+            contract_address, ..
+        }
+        | TransactionType::DestroyContract { contract_address } => accounts.push(contract_address),
+        TransactionType::Batch { operations, .. } => {
+            for (index, operation) in operations.iter().enumerate() {
+                let receipt = receipt.and_then(|r| r.operations.get(index));
+                touched_accounts(operation, receipt, accounts);
+            }
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use crate::config::static_config;
+use chrono::Utc;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicI64, Ordering};
+
+/// Source of the current time in seconds since the Unix epoch, used to stamp
+/// produced blocks and to reject blocks from the future.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> i64;
+}
+
+/// Wall-clock time
+#[derive(Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> i64 {
+        Utc::now().timestamp()
+    }
+}
+
+/// Clock that moves only when told to, for deterministic tests and replays
+#[derive(Debug, Default)]
+pub struct ManualClock {
+    now: AtomicI64,
+}
+
+impl ManualClock {
+    pub fn new(now: i64) -> Self {
+        Self {
+            now: AtomicI64::new(now),
+        }
+    }
+
+    pub fn set(&self, now: i64) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, seconds: i64) {
+        self.now.fetch_add(seconds, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> i64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Clock that moves one slot forward every time it is read, so tests can
+/// produce blocks back to back. Starts in the first slot after genesis.
+#[derive(Debug)]
+pub struct TickingClock {
+    now: AtomicI64,
+}
+
+impl Default for TickingClock {
+    fn default() -> Self {
+        Self {
+            now: AtomicI64::new(static_config::GENESIS_TIMESTAMP + static_config::SLOT_DURATION),
+        }
+    }
+}
+
+impl Clock for TickingClock {
+    fn now(&self) -> i64 {
+        self.now
+            .fetch_add(static_config::SLOT_DURATION, Ordering::SeqCst)
+    }
+}
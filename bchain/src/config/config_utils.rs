@@ -11,3 +11,11 @@ pub fn get_validators_consensus_block(epoch: usize) -> usize {
         (epoch - 1) * static_config::EPOCH_HEIGHT - 1
     }
 }
+
+/// Slot whose wall-clock window contains `timestamp`, None before genesis
+pub fn get_slot(timestamp: i64) -> Option<u64> {
+    let since_genesis = timestamp.checked_sub(static_config::GENESIS_TIMESTAMP)?;
+    u64::try_from(since_genesis / static_config::SLOT_DURATION)
+        .ok()
+        .filter(|_| since_genesis >= 0)
+}
@@ -2,3 +2,28 @@ pub const EPOCH_HEIGHT: usize = 10;
 pub const BLOCK_CHAIN_WORTH: f64 = 1000.0;
 pub const GENESIS: &str = "Genesis";
 pub const REWARD_RATE_PER_EPOCH: f64 = 0.00001;
+pub const MAX_CALL_DEPTH: usize = 8;
+pub const CONTRACT_GAS_LIMIT: u64 = 1_000_000;
+pub const STORAGE_DEPOSIT_PER_BYTE: f64 = 0.01;
+pub const HASH_GAS: u64 = 100;
+pub const HASH_WORD_GAS: u64 = 10;
+pub const ED25519_VERIFY_GAS: u64 = 2_000;
+pub const MERKLE_PROOF_STEP_GAS: u64 = 150;
+pub const RECENT_BLOCK_HASHES: usize = 256;
+pub const MAX_CONTRACT_CODE_SIZE: usize = 512 * 1024;
+pub const MAX_CONTRACT_MEMORY_PAGES: u64 = 32;
+pub const CONTRACT_WASM_SIMD: bool = false;
+pub const MODULE_CACHE_SIZE: usize = 64;
+pub const EVM_CHAIN_ID: u64 = 0x4c43;
+/// Unix time of the genesis block, slot 0 starts then
+pub const GENESIS_TIMESTAMP: i64 = 1_704_067_200;
+pub const SLOT_DURATION: i64 = 5;
+/// A block must be stamped after the median timestamp of this many previous blocks
+pub const MEDIAN_TIME_BLOCKS: usize = 11;
+/// How far ahead of the local clock a block may be stamped, in seconds
+pub const MAX_FUTURE_BLOCK_TIME: i64 = 60;
+/// Heights at which the state is kept as a snapshot for syncing nodes
+pub const SNAPSHOT_INTERVAL: usize = EPOCH_HEIGHT;
+pub const SNAPSHOT_CHUNK_ENTRIES: usize = 16;
+/// Number of recent blocks whose state a pruned node keeps
+pub const STATE_RETENTION_BLOCKS: usize = 128;
@@ -0,0 +1,33 @@
+use sha2::{Digest, Sha256};
+
+/// Length of a contract address in bytes, rendered as twice as many hex digits
+const ADDRESS_LEN: usize = 20;
+
+/// Address of the `nonce`-th contract deployed by `deployer`.
+pub fn derive(deployer: &str, nonce: u64) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update((deployer.len() as u64).to_le_bytes());
+    hasher.update(deployer.as_bytes());
+    hasher.update(nonce.to_le_bytes());
+    to_address(&hasher.finalize())
+}
+
+/// Salted address that depends only on the deployer, the salt and the code,
+/// so it is known before the deployment is included in a block.
+pub fn derive_salted(deployer: &str, salt: &[u8], code: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update([0xff]);
+    hasher.update((deployer.len() as u64).to_le_bytes());
+    hasher.update(deployer.as_bytes());
+    hasher.update((salt.len() as u64).to_le_bytes());
+    hasher.update(salt);
+    hasher.update(Sha256::digest(code));
+    to_address(&hasher.finalize())
+}
+
+fn to_address(hash: &[u8]) -> String {
+    hash[..ADDRESS_LEN]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
@@ -0,0 +1,153 @@
+use crate::primitives::Contract;
+use crate::Blockchain;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::sync::Arc;
+
+/// Previous values of the entries of a map changed while a checkpoint is
+/// alive, newest last
+pub(crate) struct Journal<K, V> {
+    changes: Vec<(K, Option<V>)>,
+    /// Shared with the live checkpoints
+    live: Arc<()>,
+}
+
+impl<K, V> Default for Journal<K, V> {
+    fn default() -> Self {
+        Self {
+            changes: Vec::new(),
+            live: Arc::new(()),
+        }
+    }
+}
+
+impl<K, V> fmt::Debug for Journal<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Journal")
+            .field("changes", &self.changes.len())
+            .finish()
+    }
+}
+
+impl<K: Eq + Hash, V> Journal<K, V> {
+    /// Records the value `key` had before a change, None if it was missing.
+    /// Nothing is kept when no checkpoint could restore it.
+    pub fn record(&mut self, key: K, previous: Option<V>) {
+        if Arc::strong_count(&self.live) > 1 {
+            self.changes.push((key, previous));
+        } else {
+            self.changes.clear();
+        }
+    }
+
+    fn mark(&mut self) -> Mark {
+        if Arc::strong_count(&self.live) == 1 {
+            self.changes.clear();
+        }
+        Mark {
+            position: self.changes.len(),
+            _live: Arc::clone(&self.live),
+        }
+    }
+
+    /// Previous values of the changes made since `mark`, newest first
+    fn undo(&mut self, mark: Mark) -> impl Iterator<Item = (K, Option<V>)> + '_ {
+        self.changes.drain(mark.position..).rev()
+    }
+}
+
+fn restore_entry<K: Eq + Hash, V>(map: &mut HashMap<K, V>, key: K, previous: Option<V>) {
+    match previous {
+        Some(value) => map.insert(key, value),
+        None => map.remove(&key),
+    };
+}
+
+/// Position in a journal, changes after it are kept while the mark lives
+struct Mark {
+    position: usize,
+    _live: Arc<()>,
+}
+
+/// Point a contract call is able to return to. Restoring it undoes what the
+/// call (and the calls it made) changed, dropping it keeps the changes.
+pub struct Checkpoint {
+    wallets: Mark,
+    contract_storage: Mark,
+    contracts: Mark,
+}
+
+impl Checkpoint {
+    pub fn new(blockchain: &mut Blockchain) -> Self {
+        Self {
+            wallets: blockchain.wallets.journal.mark(),
+            contract_storage: blockchain.storage_journal.mark(),
+            contracts: blockchain.contracts_journal.mark(),
+        }
+    }
+
+    pub fn restore(self, blockchain: &mut Blockchain) {
+        let wallets = &mut blockchain.wallets;
+        for (user, wallet) in wallets.journal.undo(self.wallets) {
+            restore_entry(&mut wallets.wallets, user, wallet);
+        }
+        for ((address, key), value) in blockchain.storage_journal.undo(self.contract_storage) {
+            let storage = blockchain
+                .contract_storage
+                .entry(address.clone())
+                .or_default();
+            restore_entry(storage, key, value);
+            if storage.is_empty() {
+                blockchain.contract_storage.remove(&address);
+            }
+        }
+        for (address, contract) in blockchain.contracts_journal.undo(self.contracts) {
+            restore_entry(&mut blockchain.contracts, address, contract);
+        }
+    }
+}
+
+impl Blockchain {
+    /// Sets or, when `value` is None, removes a storage key of a contract.
+    /// Returns the previous value.
+    pub(crate) fn write_storage(
+        &mut self,
+        address: &str,
+        key: &str,
+        value: Option<Vec<u8>>,
+    ) -> Option<Vec<u8>> {
+        let previous = match value {
+            Some(value) => self
+                .contract_storage
+                .entry(address.to_string())
+                .or_default()
+                .insert(key.to_string(), value),
+            None => self
+                .contract_storage
+                .get_mut(address)
+                .and_then(|storage| storage.remove(key)),
+        };
+        self.storage_journal
+            .record((address.to_string(), key.to_string()), previous.clone());
+        previous
+    }
+
+    /// Removes all the storage of a contract
+    pub(crate) fn clear_storage(&mut self, address: &str) {
+        for (key, value) in self.contract_storage.remove(address).unwrap_or_default() {
+            self.storage_journal
+                .record((address.to_string(), key), Some(value));
+        }
+    }
+
+    /// Deploys, replaces or, when `contract` is None, removes the contract at
+    /// `address`
+    pub(crate) fn write_contract(&mut self, address: &str, contract: Option<Contract>) {
+        let previous = match contract {
+            Some(contract) => self.contracts.insert(address.to_string(), contract),
+            None => self.contracts.remove(address),
+        };
+        self.contracts_journal.record(address.to_string(), previous);
+    }
+}
@@ -0,0 +1,445 @@
+use crate::config::static_config;
+use crate::contracts::{Checkpoint, ExecutionContext};
+use crate::primitives::{Event, Receipt, Trace, TraceStep, Vm};
+use crate::Blockchain;
+use revm_interpreter::opcode::{make_instruction_table, InstructionTable};
+use revm_interpreter::primitives::{
+    hex, keccak256, Address, Bytecode, Bytes, CancunSpec, Env, Log, B256, KECCAK_EMPTY, U256,
+};
+use revm_interpreter::{
+    CallInputs, CallOutcome, Contract, CreateOutcome, Gas, Host, InstructionResult, Interpreter,
+    InterpreterAction, InterpreterResult, LoadAccountResult, SStoreResult, SelfDestructResult,
+    SharedMemory,
+};
+use std::collections::{HashMap, HashSet};
+
+/// Balances are kept in coins, the EVM sees them in wei
+const WEI_PER_COIN: f64 = 1e18;
+
+pub fn to_wei(amount: f64) -> U256 {
+    U256::from((amount * WEI_PER_COIN) as u128)
+}
+
+pub fn from_wei(value: U256) -> f64 {
+    u128::try_from(value).map_or(f64::MAX, |wei| wei as f64 / WEI_PER_COIN)
+}
+
+/// EVM address of an account. Contract addresses already are 20 bytes of
+/// hex, other accounts use the last 20 bytes of the keccak256 of their name.
+pub fn evm_address(account: &str) -> Address {
+    if account.len() == 2 * Address::len_bytes() {
+        if let Ok(address) = account.parse() {
+            return address;
+        }
+    }
+    Address::from_slice(&keccak256(account.as_bytes())[12..])
+}
+
+/// Precompiles of the Cancun spec live at addresses 1 to 10. None of them is
+/// implemented.
+fn is_precompile(address: Address) -> bool {
+    address.0[..19].iter().all(|&byte| byte == 0) && (1..=10).contains(&address.0[19])
+}
+
+fn storage_key(index: U256) -> String {
+    format!("{:064x}", index)
+}
+
+/// Account and storage view of the EVM, backed by the blockchain's wallets
+/// and contract storage. Lives for a single transaction.
+struct EvmHost<'a> {
+    blockchain: &'a mut Blockchain,
+    env: Env,
+    /// Value of each slot before the transaction first wrote it, SSTORE
+    /// gas depends on it
+    original: HashMap<(Address, U256), U256>,
+    warm_accounts: HashSet<Address>,
+    warm_slots: HashSet<(Address, U256)>,
+    transient: HashMap<(Address, U256), U256>,
+    events: Vec<Event>,
+    /// Accounts paid by the calls that have not failed
+    paid_accounts: Vec<String>,
+    /// Reason of the last host failure, which halts the frame
+    error: Option<String>,
+}
+
+impl<'a> EvmHost<'a> {
+    fn new(blockchain: &'a mut Blockchain, context: &ExecutionContext) -> Self {
+        let mut env = Env::default();
+        env.cfg.chain_id = static_config::EVM_CHAIN_ID;
+        let block = &blockchain.block_context;
+        env.block.number = U256::from(block.height);
+        env.block.timestamp = U256::from(block.timestamp);
+        env.block.coinbase = evm_address(&block.validator);
+        env.block.gas_limit = U256::from(static_config::CONTRACT_GAS_LIMIT);
+        env.tx.caller = evm_address(&context.sender);
+        env.tx.gas_limit = context.gas_limit;
+        Self {
+            blockchain,
+            env,
+            original: HashMap::new(),
+            warm_accounts: HashSet::new(),
+            warm_slots: HashSet::new(),
+            transient: HashMap::new(),
+            events: Vec::new(),
+            paid_accounts: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Name of the account behind `address`, its hex for unknown accounts
+    fn account(&self, address: Address) -> String {
+        let hex = hex::encode(address);
+        if self.blockchain.wallets.wallets.contains_key(&hex) {
+            return hex;
+        }
+        self.blockchain
+            .wallets
+            .evm_account(&address)
+            .cloned()
+            .unwrap_or(hex)
+    }
+
+    fn evm_code(&self, address: Address) -> Option<Vec<u8>> {
+        self.blockchain
+            .contracts
+            .get(&self.account(address))
+            .filter(|contract| contract.vm == Vm::Evm)
+            .map(|contract| contract.code.clone())
+    }
+
+    fn is_cold(&mut self, address: Address) -> bool {
+        self.warm_accounts.insert(address)
+    }
+
+    fn fail(&mut self, error: String) -> Option<SStoreResult> {
+        self.error = Some(error);
+        None
+    }
+}
+
+impl Host for EvmHost<'_> {
+    fn env(&self) -> &Env {
+        &self.env
+    }
+
+    fn env_mut(&mut self) -> &mut Env {
+        &mut self.env
+    }
+
+    fn load_account(&mut self, address: Address) -> Option<LoadAccountResult> {
+        let account = self.account(address);
+        Some(LoadAccountResult {
+            is_cold: self.is_cold(address),
+            is_empty: !self.blockchain.wallets.wallets.contains_key(&account),
+        })
+    }
+
+    fn block_hash(&mut self, number: U256) -> Option<B256> {
+        let current = self.blockchain.block_context.height;
+        let hash = usize::try_from(number)
+            .ok()
+            .filter(|&height| {
+                height < current && current - height <= static_config::RECENT_BLOCK_HASHES
+            })
+            .and_then(|height| self.blockchain.chain.get_block_by_idx(height))
+            .map(|block| B256::from(block.hash.0));
+        Some(hash.unwrap_or(B256::ZERO))
+    }
+
+    fn balance(&mut self, address: Address) -> Option<(U256, bool)> {
+        let account = self.account(address);
+        let balance = self
+            .blockchain
+            .wallets
+            .wallets
+            .get(&account)
+            .map_or(U256::ZERO, |wallet| to_wei(wallet.balance));
+        Some((balance, self.is_cold(address)))
+    }
+
+    fn code(&mut self, address: Address) -> Option<(Bytes, bool)> {
+        let code = self.evm_code(address).unwrap_or_default();
+        Some((code.into(), self.is_cold(address)))
+    }
+
+    fn code_hash(&mut self, address: Address) -> Option<(B256, bool)> {
+        let hash = match self.evm_code(address) {
+            Some(code) => keccak256(code),
+            None if self.load_account(address)?.is_empty => B256::ZERO,
+            None => KECCAK_EMPTY,
+        };
+        Some((hash, self.is_cold(address)))
+    }
+
+    fn sload(&mut self, address: Address, index: U256) -> Option<(U256, bool)> {
+        let account = self.account(address);
+        let value = self
+            .blockchain
+            .contract_storage
+            .get(&account)
+            .and_then(|storage| storage.get(&storage_key(index)))
+            .map_or(U256::ZERO, |value| U256::from_be_slice(value));
+        Some((value, self.warm_slots.insert((address, index))))
+    }
+
+    fn sstore(&mut self, address: Address, index: U256, value: U256) -> Option<SStoreResult> {
+        let (present_value, is_cold) = self.sload(address, index)?;
+        let original_value = *self
+            .original
+            .entry((address, index))
+            .or_insert(present_value);
+
+        // Slots are paid for with the same deposit as wasm storage
+        let account = self.account(address);
+        let key = storage_key(index);
+        let slot_size = (key.len() + 32) as i64;
+        let size_delta = match (present_value.is_zero(), value.is_zero()) {
+            (true, false) => slot_size,
+            (false, true) => -slot_size,
+            _ => 0,
+        };
+        if let Err(e) = self
+            .blockchain
+            .wallets
+            .update_storage_deposit(&account, size_delta)
+        {
+            return self.fail(e);
+        }
+        let value_bytes = (!value.is_zero()).then(|| value.to_be_bytes_vec());
+        self.blockchain.write_storage(&account, &key, value_bytes);
+
+        Some(SStoreResult {
+            original_value,
+            present_value,
+            new_value: value,
+            is_cold,
+        })
+    }
+
+    fn tload(&mut self, address: Address, index: U256) -> U256 {
+        self.transient
+            .get(&(address, index))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn tstore(&mut self, address: Address, index: U256, value: U256) {
+        self.transient.insert((address, index), value);
+    }
+
+    fn log(&mut self, log: Log) {
+        let contract_address = self.account(log.address);
+        self.events.push(Event {
+            contract_address,
+            topics: log.data.topics().iter().map(|topic| topic.0).collect(),
+            data: log.data.data.to_vec(),
+        });
+    }
+
+    fn selfdestruct(&mut self, _address: Address, _target: Address) -> Option<SelfDestructResult> {
+        self.error = Some("SELFDESTRUCT is not supported, use DestroyContract".to_string());
+        None
+    }
+}
+
+/// Runs a frame until it returns, making the calls it asks for. Contract
+/// creation from EVM code is not supported and always fails.
+fn run_frame<'a>(
+    host: &mut EvmHost<'a>,
+    table: &InstructionTable<EvmHost<'a>>,
+    contract: Contract,
+    gas_limit: u64,
+    is_static: bool,
+    depth: usize,
+) -> InterpreterResult {
+    let mut interpreter = Interpreter::new(contract, gas_limit, is_static);
+    let mut memory = SharedMemory::new();
+    loop {
+        match interpreter.run(memory, table, host) {
+            InterpreterAction::Call { inputs } => {
+                let result = call(host, table, &inputs, depth + 1);
+                memory = interpreter.take_memory();
+                interpreter.insert_call_outcome(
+                    &mut memory,
+                    CallOutcome::new(result, inputs.return_memory_offset.clone()),
+                );
+            }
+            InterpreterAction::Create { inputs } => {
+                memory = interpreter.take_memory();
+                interpreter.insert_create_outcome(CreateOutcome::new(
+                    InterpreterResult::new(
+                        InstructionResult::Revert,
+                        Bytes::new(),
+                        Gas::new(inputs.gas_limit),
+                    ),
+                    None,
+                ));
+            }
+            InterpreterAction::EOFCreate { inputs } => {
+                memory = interpreter.take_memory();
+                interpreter.insert_eofcreate_outcome(CreateOutcome::new(
+                    InterpreterResult::new(
+                        InstructionResult::Revert,
+                        Bytes::new(),
+                        Gas::new(inputs.gas_limit),
+                    ),
+                    None,
+                ));
+            }
+            InterpreterAction::Return { result } => return result,
+            InterpreterAction::None => {
+                return InterpreterResult::new(
+                    interpreter.instruction_result,
+                    Bytes::new(),
+                    interpreter.gas,
+                )
+            }
+        }
+    }
+}
+
+/// Nested call made by a frame. State changes and events of a failed call
+/// are reverted. Accounts without EVM code only receive the value, calls to
+/// wasm contracts and precompiles fail.
+fn call<'a>(
+    host: &mut EvmHost<'a>,
+    table: &InstructionTable<EvmHost<'a>>,
+    inputs: &CallInputs,
+    depth: usize,
+) -> InterpreterResult {
+    let failure = |result| InterpreterResult::new(result, Bytes::new(), Gas::new(inputs.gas_limit));
+    if depth > static_config::MAX_CALL_DEPTH {
+        return failure(InstructionResult::CallTooDeep);
+    }
+    if is_precompile(inputs.bytecode_address) {
+        return failure(InstructionResult::PrecompileError);
+    }
+
+    let checkpoint = Checkpoint::new(host.blockchain);
+    let (events, paid_accounts) = (host.events.len(), host.paid_accounts.len());
+    if let Some(value) = inputs.value.transfer().filter(|value| !value.is_zero()) {
+        let caller = host.account(inputs.caller);
+        let target = host.account(inputs.target_address);
+        if host
+            .blockchain
+            .wallets
+            .transfer(&caller, &target, from_wei(value), 0.0)
+            .is_err()
+        {
+            return failure(InstructionResult::OutOfFunds);
+        }
+        host.paid_accounts.push(target);
+    }
+
+    let code_account = host.account(inputs.bytecode_address);
+    let mut result = match host.evm_code(inputs.bytecode_address) {
+        Some(code) => {
+            let contract = Contract::new(
+                inputs.input.clone(),
+                Bytecode::new_raw(code.into()),
+                None,
+                inputs.target_address,
+                inputs.caller,
+                inputs.value.get(),
+            );
+            run_frame(
+                host,
+                table,
+                contract,
+                inputs.gas_limit,
+                inputs.is_static,
+                depth,
+            )
+        }
+        None if host.blockchain.contracts.contains_key(&code_account) => {
+            failure(InstructionResult::Revert)
+        }
+        None => failure(InstructionResult::Stop),
+    };
+    if result.result == InstructionResult::FatalExternalError {
+        // The calling frame cannot resume after a fatal result, so the host
+        // failure is reported as a call that used all of its gas
+        result = InterpreterResult::new(
+            InstructionResult::Revert,
+            Bytes::new(),
+            Gas::new_spent(inputs.gas_limit),
+        );
+    }
+    if !result.is_ok() {
+        checkpoint.restore(host.blockchain);
+        host.events.truncate(events);
+        host.paid_accounts.truncate(paid_accounts);
+    }
+    result
+}
+
+/// Runs EVM `code` as the contract in `context`. Creation code returns the
+/// runtime code as its output. Exit code is 0 on success, 1 on revert and -1
+/// when execution halted. Traces hold only the entry and exit of the call.
+pub fn execute(
+    blockchain: &mut Blockchain,
+    code: &[u8],
+    mut context: ExecutionContext,
+) -> (Receipt, Option<Trace>) {
+    let depth = context.depth;
+    let gas_limit = context.gas_limit;
+    let contract_address = context.contract_address.clone();
+    context.record(|| TraceStep::Enter {
+        depth,
+        contract_address,
+        method: String::new(),
+        gas_limit,
+    });
+
+    let mut host = EvmHost::new(blockchain, &context);
+    let table = make_instruction_table::<EvmHost, CancunSpec>();
+    let contract = Contract::new(
+        std::mem::take(&mut context.input).into(),
+        Bytecode::new_raw(code.to_vec().into()),
+        None,
+        evm_address(&context.contract_address),
+        evm_address(&context.sender),
+        to_wei(context.value),
+    );
+    let result = run_frame(
+        &mut host,
+        &table,
+        contract,
+        gas_limit,
+        context.read_only,
+        depth,
+    );
+
+    let exit_code = match result.result {
+        _ if result.is_ok() => 0,
+        _ if result.is_revert() => 1,
+        _ => -1,
+    };
+    // Events and payments of a failed call are discarded together with its
+    // state
+    let (events, paid_accounts) = if exit_code == 0 {
+        (host.events, host.paid_accounts)
+    } else {
+        Default::default()
+    };
+    let receipt = Receipt {
+        exit_code,
+        return_data: result.output.to_vec(),
+        gas_used: result.gas.spent(),
+        error: (exit_code == -1).then(|| {
+            host.error
+                .unwrap_or_else(|| format!("EVM execution halted: {:?}", result.result))
+        }),
+        events,
+        paid_accounts,
+        ..Default::default()
+    };
+    context.record(|| TraceStep::Exit {
+        depth,
+        exit_code,
+        gas_used: receipt.gas_used,
+        trap: receipt.error.clone(),
+    });
+    (receipt, context.trace)
+}
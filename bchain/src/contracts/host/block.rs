@@ -0,0 +1,94 @@
+use super::write_memory;
+use crate::config::static_config;
+use crate::contracts::ExecutionContext;
+use crate::Blockchain;
+use wasmi::Caller;
+
+fn blockchain<'a>(blockchain_ptr_low: i32, blockchain_ptr_high: i32) -> Option<&'a Blockchain> {
+    let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
+    if blockchain_ptr == 0 {
+        println!("Error: blockchain_ptr is null");
+        return None;
+    }
+    Some(unsafe { &*(blockchain_ptr as *const Blockchain) })
+}
+
+/// Height of the block the contract is executed in.
+pub fn block_height(
+    _caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+) -> i64 {
+    blockchain(blockchain_ptr_low, blockchain_ptr_high)
+        .map_or(-1, |blockchain| blockchain.block_context.height as i64)
+}
+
+/// Timestamp of the block the contract is executed in, in seconds.
+pub fn block_timestamp(
+    _caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+) -> i64 {
+    blockchain(blockchain_ptr_low, blockchain_ptr_high)
+        .map_or(-1, |blockchain| blockchain.block_context.timestamp)
+}
+
+/// Epoch of the block the contract is executed in.
+pub fn block_epoch(
+    _caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+) -> i64 {
+    blockchain(blockchain_ptr_low, blockchain_ptr_high)
+        .map_or(-1, |blockchain| blockchain.block_context.epoch as i64)
+}
+
+/// Writes the validator producing the block to `value_ptr` and returns its
+/// length, or -1 on failure.
+pub fn block_validator(
+    caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+    value_ptr: i32,
+) -> i32 {
+    let blockchain = match blockchain(blockchain_ptr_low, blockchain_ptr_high) {
+        Some(blockchain) => blockchain,
+        None => return -1,
+    };
+    let validator = blockchain.block_context.validator.as_bytes();
+    if !write_memory(caller, value_ptr, validator) {
+        return -1;
+    }
+    validator.len() as i32
+}
+
+/// Writes the 32-byte hash of the block at `height` to `value_ptr`. Only the
+/// last `RECENT_BLOCK_HASHES` blocks are available. Returns 0 on success and 1
+/// on failure.
+pub fn block_hash(
+    caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+    height: i64,
+    value_ptr: i32,
+) -> i32 {
+    let blockchain = match blockchain(blockchain_ptr_low, blockchain_ptr_high) {
+        Some(blockchain) => blockchain,
+        None => return 1,
+    };
+    let current = blockchain.block_context.height as i64;
+    if height < 0
+        || height >= current
+        || height < current - static_config::RECENT_BLOCK_HASHES as i64
+    {
+        return 1;
+    }
+    let hash = match blockchain.chain.get_block_by_idx(height as usize) {
+        Some(block) => block.hash.0,
+        None => return 1,
+    };
+    if !write_memory(caller, value_ptr, &hash) {
+        return 1;
+    }
+    0
+}
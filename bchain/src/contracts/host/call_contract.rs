@@ -0,0 +1,111 @@
+use super::read_memory;
+use crate::config::static_config;
+use crate::contracts::{self, Checkpoint, ExecutionContext};
+use crate::Blockchain;
+use wasmi::Caller;
+
+/// Runs `method` of the contract at `address` as a nested call made by the
+/// current contract. Returns the callee's exit code, or -1 if the call could
+/// not be made or trapped. State changes of a failed callee are reverted and
+/// its output is available through `read_call_output`. Calls made from a
+/// read-only call are read-only too and cannot carry value.
+#[allow(clippy::too_many_arguments)]
+pub fn call_contract(
+    caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+    address_ptr: i32,
+    address_len: i32,
+    method_ptr: i32,
+    method_len: i32,
+    input_ptr: i32,
+    input_len: i32,
+    value: f64,
+) -> i32 {
+    let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
+    if blockchain_ptr == 0 {
+        println!("Error: blockchain_ptr is null");
+        return -1;
+    }
+    let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
+    let read_str = |ptr: i32, len: i32| {
+        read_memory(caller, ptr, len).and_then(|bytes| String::from_utf8(bytes).ok())
+    };
+    let (address, method) = match (
+        read_str(address_ptr, address_len),
+        read_str(method_ptr, method_len),
+    ) {
+        (Some(address), Some(method)) => (address, method),
+        _ => return -1,
+    };
+    let input = match read_memory(caller, input_ptr, input_len) {
+        Some(input) => input,
+        None => return -1,
+    };
+
+    caller.data_mut().call_output.clear();
+    let depth = caller.data().depth + 1;
+    if depth > static_config::MAX_CALL_DEPTH {
+        println!("Error: call depth {} exceeded", depth);
+        return -1;
+    }
+    if !value.is_finite() || value < 0.0 || (value > 0.0 && caller.data().read_only) {
+        return -1;
+    }
+    let (vm, code) = match blockchain.contracts.get(&address) {
+        Some(contract) => (contract.vm, contract.code.clone()),
+        None => return -1,
+    };
+
+    let self_address = caller.data().contract_address.clone();
+    let checkpoint = Checkpoint::new(blockchain);
+    if blockchain
+        .wallets
+        .transfer(&self_address, &address, value, 0.0)
+        .is_err()
+    {
+        return -1;
+    }
+
+    // The callee may spend only what is left of the caller's gas
+    let gas_limit = caller.get_fuel().unwrap_or(0);
+    let context = ExecutionContext {
+        depth,
+        gas_limit,
+        read_only: caller.data().read_only,
+        value,
+        ..ExecutionContext::new(&address, &self_address, &input)
+    };
+    let result = if caller.data().trace.is_some() {
+        contracts::execute_traced(blockchain, vm, &code, &method, context).map(
+            |(receipt, trace)| {
+                caller.data_mut().record_all(trace);
+                receipt
+            },
+        )
+    } else {
+        contracts::execute(blockchain, vm, &code, &method, context)
+    };
+    match result {
+        Ok(receipt) => {
+            if receipt.exit_code == 0 {
+                let context = caller.data_mut();
+                context.events.extend(receipt.events);
+                context.paid_accounts.extend(receipt.paid_accounts);
+                if value > 0.0 {
+                    context.paid_accounts.push(address);
+                }
+            } else {
+                checkpoint.restore(blockchain);
+            }
+            let _ = caller.set_fuel(gas_limit.saturating_sub(receipt.gas_used));
+            caller.data_mut().call_output = receipt.return_data;
+            receipt.exit_code
+        }
+        Err(e) => {
+            println!("Error: nested call to {} failed: {}", address, e);
+            checkpoint.restore(blockchain);
+            -1
+        }
+    }
+}
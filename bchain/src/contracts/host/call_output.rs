@@ -0,0 +1,17 @@
+use super::write_memory;
+use crate::contracts::ExecutionContext;
+use wasmi::Caller;
+
+pub fn call_output_len(caller: &mut Caller<ExecutionContext>) -> i32 {
+    caller.data().call_output.len() as i32
+}
+
+/// Copies the output of the last nested call to `value_ptr` and returns its
+/// length, or -1 on failure.
+pub fn read_call_output(caller: &mut Caller<ExecutionContext>, value_ptr: i32) -> i32 {
+    let output = caller.data().call_output.clone();
+    if !write_memory(caller, value_ptr, &output) {
+        return -1; // Not enough space in memory
+    }
+    output.len() as i32
+}
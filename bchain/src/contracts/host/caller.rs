@@ -0,0 +1,13 @@
+use super::write_memory;
+use crate::contracts::ExecutionContext;
+use wasmi::Caller;
+
+/// Writes the address of the account that called the contract at `value_ptr`
+/// and returns its length, or -1 on failure.
+pub fn caller(caller: &mut Caller<ExecutionContext>, value_ptr: i32) -> i32 {
+    let sender = caller.data().sender.clone().into_bytes();
+    if !write_memory(caller, value_ptr, &sender) {
+        return -1; // Not enough space in memory
+    }
+    sender.len() as i32
+}
@@ -1,16 +1,16 @@
-use wasmi::{Caller, Extern};
+use super::read_memory;
+use crate::contracts::ExecutionContext;
+use wasmi::Caller;
 
-pub fn debug(caller: Caller<(String, String)>, msg_ptr: i32, msg_len: i32, value: u32) {
-    let memory = match caller.get_export("memory").and_then(Extern::into_memory) {
-        Some(mem) => mem,
+pub fn debug(caller: &mut Caller<ExecutionContext>, msg_ptr: i32, msg_len: i32, value: u32) {
+    let msg_bytes = match read_memory(caller, msg_ptr, msg_len) {
+        Some(bytes) => bytes,
         None => {
-            println!("Debug error: No memory export found");
+            println!("Debug error: Message out of memory bounds");
             return;
         }
     };
-
-    let msg_bytes = &memory.data(&caller)[msg_ptr as usize..(msg_ptr + msg_len) as usize];
-    let msg = match String::from_utf8(msg_bytes.to_vec()) {
+    let msg = match String::from_utf8(msg_bytes) {
         Ok(s) => s,
         Err(_) => {
             println!("Debug error: Invalid UTF-8 string");
@@ -0,0 +1,36 @@
+use super::{charge_gas, read_memory};
+use crate::config::static_config;
+use crate::contracts::ExecutionContext;
+use ed25519_dalek::{Signature, VerifyingKey};
+use wasmi::{Caller, Error};
+
+/// Checks the 64-byte signature at `signature_ptr` of the message at `msg_ptr`
+/// against the 32-byte public key at `public_key_ptr`. Returns 0 if the
+/// signature is valid and 1 otherwise.
+pub fn ed25519_verify(
+    caller: &mut Caller<ExecutionContext>,
+    public_key_ptr: i32,
+    signature_ptr: i32,
+    msg_ptr: i32,
+    msg_len: i32,
+) -> Result<i32, Error> {
+    charge_gas(&mut *caller, static_config::ED25519_VERIFY_GAS)?;
+    let read = |ptr: i32, len: i32| read_memory(caller, ptr, len);
+    let (public_key, signature, msg) = match (
+        read(public_key_ptr, 32),
+        read(signature_ptr, 64),
+        read(msg_ptr, msg_len),
+    ) {
+        (Some(public_key), Some(signature), Some(msg)) => (public_key, signature, msg),
+        _ => return Ok(1),
+    };
+    let public_key = match VerifyingKey::from_bytes(public_key.as_slice().try_into().unwrap()) {
+        Ok(key) => key,
+        Err(_) => return Ok(1),
+    };
+    let signature = Signature::from_bytes(signature.as_slice().try_into().unwrap());
+    match public_key.verify_strict(&msg, &signature) {
+        Ok(()) => Ok(0),
+        Err(_) => Ok(1),
+    }
+}
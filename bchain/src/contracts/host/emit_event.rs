@@ -0,0 +1,44 @@
+use super::read_memory;
+use crate::contracts::ExecutionContext;
+use crate::primitives::Event;
+use wasmi::Caller;
+
+const MAX_TOPICS: i32 = 4;
+const TOPIC_LEN: usize = 32;
+
+/// Records an event with `topics_count` 32-byte topics read from `topics_ptr`.
+/// The event reaches the receipt only if the call succeeds.
+pub fn emit_event(
+    caller: &mut Caller<ExecutionContext>,
+    topics_ptr: i32,
+    topics_count: i32,
+    data_ptr: i32,
+    data_len: i32,
+) -> i32 {
+    if caller.data().read_only {
+        println!("Error: emit_event in a read-only call");
+        return 1;
+    }
+    if !(0..=MAX_TOPICS).contains(&topics_count) {
+        return 1;
+    }
+    let topics = match read_memory(caller, topics_ptr, topics_count * TOPIC_LEN as i32) {
+        Some(bytes) => bytes
+            .chunks_exact(TOPIC_LEN)
+            .map(|topic| topic.try_into().unwrap())
+            .collect(),
+        None => return 1,
+    };
+    let data = match read_memory(caller, data_ptr, data_len) {
+        Some(bytes) => bytes,
+        None => return 1,
+    };
+
+    let contract_address = caller.data().contract_address.clone();
+    caller.data_mut().events.push(Event {
+        contract_address,
+        topics,
+        data,
+    });
+    0 // Success
+}
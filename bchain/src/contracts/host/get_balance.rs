@@ -1,8 +1,10 @@
+use super::read_memory;
+use crate::contracts::ExecutionContext;
 use crate::Blockchain;
-use wasmi::{Caller, Extern};
+use wasmi::Caller;
 
 pub fn get_balance(
-    caller: Caller<(String, String)>,
+    caller: &mut Caller<ExecutionContext>,
     blockchain_ptr_low: i32,
     blockchain_ptr_high: i32,
     user_ptr: i32,
@@ -11,12 +13,10 @@ pub fn get_balance(
     let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
     let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
 
-    let memory = match caller.get_export("memory").and_then(Extern::into_memory) {
-        Some(mem) => mem,
+    let user_bytes = match read_memory(caller, user_ptr, user_len) {
+        Some(bytes) => bytes,
         None => return 0.0,
     };
-    let user_bytes =
-        memory.data(&caller)[user_ptr as usize..(user_ptr + user_len) as usize].to_vec();
     let user = match String::from_utf8(user_bytes) {
         Ok(u) => u,
         Err(_) => return 0.0,
@@ -0,0 +1,49 @@
+use super::{charge_gas, read_memory, write_memory};
+use crate::config::static_config;
+use crate::contracts::ExecutionContext;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use wasmi::{Caller, Error};
+
+/// Writes the sha256 digest of the data at `data_ptr` to `out_ptr`.
+/// Returns 0 on success and 1 on failure.
+pub fn sha256(
+    caller: &mut Caller<ExecutionContext>,
+    data_ptr: i32,
+    data_len: i32,
+    out_ptr: i32,
+) -> Result<i32, Error> {
+    digest::<Sha256>(caller, data_ptr, data_len, out_ptr)
+}
+
+/// Writes the keccak256 digest of the data at `data_ptr` to `out_ptr`.
+/// Returns 0 on success and 1 on failure.
+pub fn keccak256(
+    caller: &mut Caller<ExecutionContext>,
+    data_ptr: i32,
+    data_len: i32,
+    out_ptr: i32,
+) -> Result<i32, Error> {
+    digest::<Keccak256>(caller, data_ptr, data_len, out_ptr)
+}
+
+fn digest<D: Digest>(
+    caller: &mut Caller<ExecutionContext>,
+    data_ptr: i32,
+    data_len: i32,
+    out_ptr: i32,
+) -> Result<i32, Error> {
+    let words = (data_len.max(0) as u64).div_ceil(32);
+    charge_gas(
+        &mut *caller,
+        static_config::HASH_GAS + words * static_config::HASH_WORD_GAS,
+    )?;
+    let data = match read_memory(caller, data_ptr, data_len) {
+        Some(data) => data,
+        None => return Ok(1),
+    };
+    if !write_memory(caller, out_ptr, &D::digest(data)) {
+        return Ok(1);
+    }
+    Ok(0)
+}
@@ -0,0 +1,16 @@
+use super::write_memory;
+use crate::contracts::ExecutionContext;
+use wasmi::Caller;
+
+pub fn input_len(caller: &mut Caller<ExecutionContext>) -> i32 {
+    caller.data().input.len() as i32
+}
+
+/// Copies the call input to `value_ptr` and returns its length, or -1 on failure.
+pub fn read_input(caller: &mut Caller<ExecutionContext>, value_ptr: i32) -> i32 {
+    let input = caller.data().input.clone();
+    if !write_memory(caller, value_ptr, &input) {
+        return -1; // Not enough space in memory
+    }
+    input.len() as i32
+}
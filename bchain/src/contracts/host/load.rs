@@ -1,8 +1,11 @@
+use super::{read_memory, write_memory};
+use crate::contracts::ExecutionContext;
+use crate::primitives::TraceStep;
 use crate::Blockchain;
-use wasmi::{Caller, Extern};
+use wasmi::Caller;
 
 pub fn load(
-    mut caller: Caller<(String, String)>,
+    caller: &mut Caller<ExecutionContext>,
     blockchain_ptr_low: i32,
     blockchain_ptr_high: i32,
     key_ptr: i32,
@@ -15,33 +18,33 @@ pub fn load(
         return -1;
     }
     let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
-    let memory = match caller.get_export("memory").and_then(Extern::into_memory) {
-        Some(mem) => mem,
-        None => return -1,
-    };
 
     // Read the key
-    let key_bytes = &memory.data(&caller)[key_ptr as usize..(key_ptr + key_len) as usize];
-    let key = match String::from_utf8(key_bytes.to_vec()) {
-        Ok(s) => s,
-        Err(_) => return -1,
+    let key = match read_memory(caller, key_ptr, key_len).map(String::from_utf8) {
+        Some(Ok(key)) => key,
+        _ => return -1,
     };
 
     // Get the contract address from the caller data
-    let contract_address = caller.data().0.clone();
+    let contract_address = caller.data().contract_address.clone();
 
     // Look up the value
     let storage = blockchain.contract_storage.get(&contract_address);
+    let depth = caller.data().depth;
+    caller.data_mut().record(|| TraceStep::StorageRead {
+        depth,
+        contract_address: contract_address.clone(),
+        key: key.clone(),
+        value: storage.and_then(|s| s.get(&key)).cloned(),
+    });
     let value = match storage.and_then(|s| s.get(&key)) {
         Some(v) => v,
         None => return -1, // Key not found
     };
 
     // Write the value to the specified location
-    if (value_ptr as usize) + value.len() > memory.data(&caller).len() {
+    if !write_memory(caller, value_ptr, value) {
         return -1; // Not enough space in memory
     }
-    memory.data_mut(&mut caller)[value_ptr as usize..(value_ptr as usize) + value.len()]
-        .copy_from_slice(value);
     value.len() as i32 // Return the length of the value
 }
@@ -1,15 +1,98 @@
+mod block;
+mod call_contract;
+mod call_output;
+mod caller;
 mod debug;
+mod ed25519_verify;
+mod emit_event;
 mod get_balance;
+mod hash;
+mod input;
 mod load;
+mod remove;
+mod self_address;
+mod self_balance;
+mod set_return;
 mod stake;
 mod store;
 mod transfer;
 mod unstake;
+mod verify_merkle_proof;
 
+pub use block::{block_epoch, block_hash, block_height, block_timestamp, block_validator};
+pub use call_contract::call_contract;
+pub use call_output::{call_output_len, read_call_output};
+pub use caller::caller;
 pub use debug::debug;
+pub use ed25519_verify::ed25519_verify;
+pub use emit_event::emit_event;
 pub use get_balance::get_balance;
+pub use hash::{keccak256, sha256};
+pub use input::{input_len, read_input};
 pub use load::load;
+pub use remove::remove;
+pub use self_address::self_address;
+pub use self_balance::self_balance;
+pub use set_return::set_return;
 pub use stake::stake;
 pub use store::store;
 pub use transfer::transfer;
 pub use unstake::unstake;
+pub use verify_merkle_proof::verify_merkle_proof;
+
+use crate::contracts::ExecutionContext;
+use std::ops::Range;
+use wasmi::core::TrapCode;
+use wasmi::{Caller, Error, Extern};
+
+/// Charges the fixed price of a precompile, trapping like the engine does
+/// when the call runs out of gas.
+fn charge_gas(caller: &mut Caller<ExecutionContext>, gas: u64) -> Result<(), Error> {
+    let fuel = caller.get_fuel()?;
+    if fuel < gas {
+        caller.set_fuel(0)?;
+        return Err(TrapCode::OutOfFuel.into());
+    }
+    caller.set_fuel(fuel - gas)?;
+    Ok(())
+}
+
+/// Range of `len` bytes at `ptr`, None when either is negative or the end
+/// overflows
+fn memory_range(ptr: i32, len: i32) -> Option<Range<usize>> {
+    let start = usize::try_from(ptr).ok()?;
+    Some(start..start.checked_add(usize::try_from(len).ok()?)?)
+}
+
+/// Copy of `len` bytes of the contract's memory at `ptr`, None when the
+/// range is invalid or out of bounds
+fn read_memory(caller: &Caller<ExecutionContext>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let memory = caller.get_export("memory").and_then(Extern::into_memory)?;
+    memory
+        .data(caller)
+        .get(memory_range(ptr, len)?)
+        .map(<[u8]>::to_vec)
+}
+
+/// Copies `bytes` into the contract's memory at `ptr`. Returns false when the
+/// range is invalid or out of bounds.
+fn write_memory(caller: &mut Caller<ExecutionContext>, ptr: i32, bytes: &[u8]) -> bool {
+    let memory = match caller.get_export("memory").and_then(Extern::into_memory) {
+        Some(mem) => mem,
+        None => return false,
+    };
+    let range = match i32::try_from(bytes.len())
+        .ok()
+        .and_then(|len| memory_range(ptr, len))
+    {
+        Some(range) => range,
+        None => return false,
+    };
+    match memory.data_mut(caller).get_mut(range) {
+        Some(out) => {
+            out.copy_from_slice(bytes);
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,51 @@
+use super::read_memory;
+use crate::contracts::ExecutionContext;
+use crate::primitives::TraceStep;
+use crate::Blockchain;
+use wasmi::Caller;
+
+/// Deletes a storage key and releases its deposit. Returns 1 if the key is missing.
+pub fn remove(
+    caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+    key_ptr: i32,
+    key_len: i32,
+) -> i32 {
+    if caller.data().read_only {
+        println!("Error: remove in a read-only call");
+        return 1;
+    }
+    let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
+    if blockchain_ptr == 0 {
+        println!("Error: blockchain_ptr is null");
+        return 1;
+    }
+    let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
+
+    let key = match read_memory(caller, key_ptr, key_len).map(String::from_utf8) {
+        Some(Ok(key)) => key,
+        _ => return 1,
+    };
+
+    let contract_address = caller.data().contract_address.clone();
+    let removed = blockchain.write_storage(&contract_address, &key, None);
+    let depth = caller.data().depth;
+    caller.data_mut().record(|| TraceStep::StorageWrite {
+        depth,
+        contract_address: contract_address.clone(),
+        key: key.clone(),
+        before: removed.clone(),
+        after: None,
+    });
+    match removed {
+        Some(value) => {
+            let size = (key.len() + value.len()) as i64;
+            blockchain
+                .wallets
+                .update_storage_deposit(&contract_address, -size)
+                .map_or(1, |_| 0)
+        }
+        None => 1,
+    }
+}
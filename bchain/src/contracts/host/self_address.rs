@@ -0,0 +1,13 @@
+use super::write_memory;
+use crate::contracts::ExecutionContext;
+use wasmi::Caller;
+
+/// Writes the address of the running contract at `value_ptr` and returns its
+/// length, or -1 on failure.
+pub fn self_address(caller: &mut Caller<ExecutionContext>, value_ptr: i32) -> i32 {
+    let address = caller.data().contract_address.clone().into_bytes();
+    if !write_memory(caller, value_ptr, &address) {
+        return -1; // Not enough space in memory
+    }
+    address.len() as i32
+}
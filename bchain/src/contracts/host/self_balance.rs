@@ -0,0 +1,23 @@
+use crate::contracts::ExecutionContext;
+use crate::Blockchain;
+use wasmi::Caller;
+
+pub fn self_balance(
+    caller: &mut Caller<ExecutionContext>,
+    blockchain_ptr_low: i32,
+    blockchain_ptr_high: i32,
+) -> f64 {
+    let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
+    if blockchain_ptr == 0 {
+        println!("Error: blockchain_ptr is null");
+        return 0.0;
+    }
+    let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
+    let contract_address = &caller.data().contract_address;
+    blockchain
+        .wallets
+        .wallets
+        .get(contract_address)
+        .map(|w| w.balance)
+        .unwrap_or(0.0)
+}
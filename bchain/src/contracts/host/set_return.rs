@@ -0,0 +1,13 @@
+use super::read_memory;
+use crate::contracts::ExecutionContext;
+use wasmi::Caller;
+
+/// Replaces the data returned to the caller of the contract.
+pub fn set_return(caller: &mut Caller<ExecutionContext>, value_ptr: i32, value_len: i32) -> i32 {
+    let value = match read_memory(caller, value_ptr, value_len) {
+        Some(bytes) => bytes,
+        None => return 1,
+    };
+    caller.data_mut().return_data = value;
+    0 // Success
+}
@@ -1,30 +1,31 @@
+use crate::contracts::ExecutionContext;
 use crate::Blockchain;
 use wasmi::Caller;
 
+/// Stakes `amount` out of the contract's own balance. Returns 1 if the
+/// balance does not cover it.
 pub fn stake(
-    caller: Caller<(String, String)>,
+    caller: &mut Caller<ExecutionContext>,
     blockchain_ptr_low: i32,
     blockchain_ptr_high: i32,
     amount: f64,
 ) -> i32 {
+    if caller.data().read_only {
+        println!("Error: stake in a read-only call");
+        return 1;
+    }
     let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
     if blockchain_ptr == 0 {
         println!("Error: blockchain_ptr is null");
         return 1;
     }
     let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
-    let user = caller.data().1.clone();
-    let wallet = match blockchain.wallets.wallets.get_mut(&user) {
-        Some(wallet) => wallet,
-        None => {
-            println!("Error: User {} not found", user);
-            return 1;
+    let user = caller.data().contract_address.clone();
+    match blockchain.wallets.stake(&user, amount, 0.0) {
+        Ok(()) => 0,
+        Err(e) => {
+            println!("Error: {}", e);
+            1
         }
-    };
-    if wallet.balance < amount {
-        return 1;
     }
-    wallet.balance -= amount;
-    wallet.staked += amount;
-    0
 }
@@ -1,8 +1,11 @@
+use super::read_memory;
+use crate::contracts::ExecutionContext;
+use crate::primitives::TraceStep;
 use crate::Blockchain;
-use wasmi::{Caller, Extern};
+use wasmi::Caller;
 
 pub fn store(
-    caller: Caller<(String, String)>,
+    caller: &mut Caller<ExecutionContext>,
     blockchain_ptr_low: i32,
     blockchain_ptr_high: i32,
     key_ptr: i32,
@@ -10,6 +13,10 @@ pub fn store(
     value_ptr: i32,
     value_len: i32,
 ) -> i32 {
+    if caller.data().read_only {
+        println!("Error: store in a read-only call");
+        return 1;
+    }
     let blockchain_ptr = ((blockchain_ptr_high as u64) << 32) | (blockchain_ptr_low as u32 as u64);
     if blockchain_ptr == 0 {
         println!("Error: blockchain_ptr is null");
@@ -17,25 +24,45 @@ pub fn store(
     }
     let blockchain: &mut Blockchain = unsafe { &mut *(blockchain_ptr as *mut Blockchain) };
 
-    let memory = match caller.get_export("memory").and_then(Extern::into_memory) {
-        Some(mem) => mem,
-        None => return 1,
+    let key = match read_memory(caller, key_ptr, key_len).map(String::from_utf8) {
+        Some(Ok(key)) => key,
+        _ => return 1,
     };
 
-    let key_bytes = &memory.data(&caller)[key_ptr as usize..(key_ptr + key_len) as usize];
-    let key = match String::from_utf8(key_bytes.to_vec()) {
-        Ok(s) => s,
-        Err(_) => return 1,
+    let value = match read_memory(caller, value_ptr, value_len) {
+        Some(bytes) => bytes,
+        None => return 1,
     };
 
-    let value_bytes = &memory.data(&caller)[value_ptr as usize..(value_ptr + value_len) as usize];
-    let value = value_bytes.to_vec();
-
-    let contract_address = caller.data().0.clone();
-    let storage = blockchain
+    // Storage is paid for with a deposit per byte of key and value
+    let contract_address = caller.data().contract_address.clone();
+    let previous_size = blockchain
         .contract_storage
-        .entry(contract_address)
-        .or_default();
-    storage.insert(key, value);
+        .get(&contract_address)
+        .and_then(|storage| storage.get(&key))
+        .map_or(0, |previous| key.len() + previous.len());
+    let size_delta = (key.len() + value.len()) as i64 - previous_size as i64;
+    if let Err(e) = blockchain
+        .wallets
+        .update_storage_deposit(&contract_address, size_delta)
+    {
+        println!("Error: {}", e);
+        return 1;
+    }
+
+    let depth = caller.data().depth;
+    caller.data_mut().record(|| TraceStep::StorageWrite {
+        depth,
+        contract_address: contract_address.clone(),
+        key: key.clone(),
+        before: blockchain
+            .contract_storage
+            .get(&contract_address)
+            .and_then(|storage| storage.get(&key))
+            .cloned(),
+        after: Some(value.clone()),
+    });
+
+    blockchain.write_storage(&contract_address, &key, Some(value));
     0 // Success
 }
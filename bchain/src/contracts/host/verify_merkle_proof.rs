@@ -0,0 +1,55 @@
+use super::{charge_gas, read_memory};
+use crate::config::static_config;
+use crate::contracts::ExecutionContext;
+use crate::patricia_merkle_trie::merkle_tree;
+use crate::primitives::Hash;
+use wasmi::{Caller, Error};
+
+const HASH_LEN: usize = 32;
+const STEP_LEN: usize = HASH_LEN + 1;
+
+/// Checks that the 32-byte leaf hash at `leaf_ptr` belongs to the state tree
+/// with the 32-byte root at `root_ptr`. The proof is `proof_len` steps of a
+/// 32-byte sibling hash followed by 1 if the current hash is the left child
+/// and 0 otherwise. Returns 0 if the proof is valid and 1 otherwise.
+pub fn verify_merkle_proof(
+    caller: &mut Caller<ExecutionContext>,
+    leaf_ptr: i32,
+    proof_ptr: i32,
+    proof_len: i32,
+    root_ptr: i32,
+) -> Result<i32, Error> {
+    let proof_steps = match usize::try_from(proof_len) {
+        Ok(steps) => steps,
+        Err(_) => return Ok(1),
+    };
+    let (proof_bytes, gas) = match (
+        proof_steps
+            .checked_mul(STEP_LEN)
+            .and_then(|len| i32::try_from(len).ok()),
+        static_config::MERKLE_PROOF_STEP_GAS.checked_mul(proof_steps as u64),
+    ) {
+        (Some(proof_bytes), Some(gas)) => (proof_bytes, gas),
+        _ => return Ok(1),
+    };
+    charge_gas(&mut *caller, gas)?;
+
+    let (leaf, proof, root) = match (
+        read_memory(caller, leaf_ptr, HASH_LEN as i32),
+        read_memory(caller, proof_ptr, proof_bytes),
+        read_memory(caller, root_ptr, HASH_LEN as i32),
+    ) {
+        (Some(leaf), Some(proof), Some(root)) => (leaf, proof, root),
+        _ => return Ok(1),
+    };
+    let hash = |bytes: &[u8]| Hash(bytes.try_into().unwrap());
+    let proof: Vec<(Hash, bool)> = proof
+        .chunks_exact(STEP_LEN)
+        .map(|step| (hash(&step[..HASH_LEN]), step[HASH_LEN] == 1))
+        .collect();
+    if merkle_tree::root_from_proof(hash(&leaf), &proof) == hash(&root) {
+        Ok(0)
+    } else {
+        Ok(1)
+    }
+}
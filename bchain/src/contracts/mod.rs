@@ -1,44 +1,219 @@
+pub mod address;
+mod checkpoint;
+mod evm;
 mod host;
+mod module_cache;
+mod validation;
 
+pub use checkpoint::Checkpoint;
+pub(crate) use checkpoint::Journal;
+pub(crate) use evm::evm_address;
+pub use module_cache::ModuleCache;
+pub use validation::validate;
+
+use crate::config::static_config;
+use crate::primitives::{Event, Receipt, Trace, TraceStep, Vm};
 use crate::Blockchain;
-use wasmi::{Engine, Extern, Func, Linker, Module, Store};
+use sha2::{Digest, Sha256};
+use wasmi::{Caller, Error, Extern, Func, Linker, Store, StoreLimits, StoreLimitsBuilder};
 
-pub fn execute(
-    blockchain: &mut Blockchain,
-    code: &[u8],
-    contract_address: &str,
-    sender: &str,
-) -> Result<(), String> {
-    // Initialize the Wasm engine and store
-    let engine = Engine::default();
-    let module =
-        Module::new(&engine, code).map_err(|e| format!("Failed to laod Wasm module: {:?}", e))?;
-    let mut store: Store<(String, String)> =
-        Store::new(&engine, (contract_address.to_string(), sender.to_string()));
+/// Block a contract is executed in, fixed before the block's transactions run
+/// so that producing and importing the block see the same values.
+#[derive(Debug, Default, Clone)]
+pub struct BlockContext {
+    pub height: usize,
+    pub timestamp: i64,
+    pub epoch: usize,
+    pub validator: String,
+}
 
-    // Create a linker and define host functions
-    let mut linker: Linker<(String, String)> = Linker::new(&engine);
+/// Per-call data available to host functions through `Caller::data`.
+#[derive(Debug, Default)]
+pub struct ExecutionContext {
+    pub contract_address: String,
+    pub sender: String,
+    pub input: Vec<u8>,
+    /// Coins sent with the call, already credited to the contract
+    pub value: f64,
+    pub return_data: Vec<u8>,
+    /// Output of the most recent nested `call_contract`
+    pub call_output: Vec<u8>,
+    /// Number of contract calls below this one, 0 for a call made by a transaction
+    pub depth: usize,
+    /// Fuel available to the call, unused fuel is not refunded
+    pub gas_limit: u64,
+    /// Events emitted by this call and its successful nested calls
+    pub events: Vec<Event>,
+    /// Accounts paid by this call and its successful nested calls
+    pub paid_accounts: Vec<String>,
+    /// Set for queries, host functions that change state fail
+    pub read_only: bool,
+    /// Bounds memory growth, set when the call starts
+    pub limits: StoreLimits,
+    /// Steps recorded so far when the call is traced
+    pub trace: Option<Trace>,
+}
+
+impl ExecutionContext {
+    pub fn new(contract_address: &str, sender: &str, input: &[u8]) -> Self {
+        Self {
+            contract_address: contract_address.to_string(),
+            sender: sender.to_string(),
+            input: input.to_vec(),
+            gas_limit: static_config::CONTRACT_GAS_LIMIT,
+            ..Default::default()
+        }
+    }
 
-    let get_balance = Func::wrap(&mut store, host::get_balance);
-    linker.define("env", "get_balance", get_balance).unwrap();
+    /// Adds a step to the trace, `step` is only built when tracing
+    pub fn record(&mut self, step: impl FnOnce() -> TraceStep) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.steps.push(step());
+        }
+    }
 
-    let transfer = Func::wrap(&mut store, host::transfer);
-    linker.define("env", "transfer", transfer).unwrap();
+    /// Appends the trace of a nested call
+    pub fn record_all(&mut self, nested: Trace) {
+        if let Some(trace) = self.trace.as_mut() {
+            trace.steps.extend(nested.steps);
+        }
+    }
+}
+
+const WASM_PAGE_SIZE: usize = 65_536;
+
+/// Halves of the blockchain pointer, left out of traces as they change between runs
+const BLOCKCHAIN_PTR_ARGS: [&str; 2] = ["lo", "hi"];
+
+/// Registers host functions under `env`, recording every call when tracing.
+macro_rules! define_host_functions {
+    ($store:ident, $linker:ident; $($name:ident($($arg:ident: $ty:ty),*) -> $ret:ty;)*) => {$(
+        let func = Func::wrap(
+            &mut $store,
+            |mut caller: Caller<ExecutionContext>, $($arg: $ty),*| -> $ret {
+                let gas_before = caller.get_fuel().unwrap_or(0);
+                let result = host::$name(&mut caller, $($arg),*);
+                let gas_used = gas_before.saturating_sub(caller.get_fuel().unwrap_or(0));
+                let depth = caller.data().depth;
+                caller.data_mut().record(|| TraceStep::HostCall {
+                    depth,
+                    name: stringify!($name).to_string(),
+                    args: [$((stringify!($arg), format!("{:?}", $arg))),*]
+                        .into_iter()
+                        .filter(|(name, _)| !BLOCKCHAIN_PTR_ARGS.contains(name))
+                        .map(|(name, value)| (name.to_string(), value))
+                        .collect(),
+                    result: format!("{:?}", result),
+                    gas_used,
+                });
+                result
+            },
+        );
+        $linker.define("env", stringify!($name), func).unwrap();
+    )*};
+}
+
+pub fn code_hash(code: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(code))
+}
 
-    let store_func = Func::wrap(&mut store, host::store);
-    linker.define("env", "store", store_func).unwrap();
+/// Runs `method` of a contract on the machine its code was written for. EVM
+/// code has a single entry point taking the input as calldata, so `method` is
+/// ignored there. Errors are returned only when the call could not be started,
+/// a trap (including running out of gas) yields a receipt with exit code -1.
+/// The caller is responsible for reverting state of failed calls.
+pub fn execute(
+    blockchain: &mut Blockchain,
+    vm: Vm,
+    code: &[u8],
+    method: &str,
+    context: ExecutionContext,
+) -> Result<Receipt, String> {
+    run(blockchain, vm, code, method, context).map(|(receipt, _)| receipt)
+}
 
-    let load_func = Func::wrap(&mut store, host::load);
-    linker.define("env", "load", load_func).unwrap();
+/// Like `execute`, but also records the host calls and storage accesses of
+/// the call and of its nested calls.
+pub fn execute_traced(
+    blockchain: &mut Blockchain,
+    vm: Vm,
+    code: &[u8],
+    method: &str,
+    context: ExecutionContext,
+) -> Result<(Receipt, Trace), String> {
+    let context = ExecutionContext {
+        trace: Some(Trace::default()),
+        ..context
+    };
+    run(blockchain, vm, code, method, context).map(|(receipt, trace)| (receipt, trace.unwrap()))
+}
 
-    let stake_func = Func::wrap(&mut store, host::stake);
-    linker.define("env", "stake", stake_func).unwrap();
+fn run(
+    blockchain: &mut Blockchain,
+    vm: Vm,
+    code: &[u8],
+    method: &str,
+    context: ExecutionContext,
+) -> Result<(Receipt, Option<Trace>), String> {
+    match vm {
+        Vm::Wasm => run_wasm(blockchain, code, method, context),
+        Vm::Evm => Ok(evm::execute(blockchain, code, context)),
+    }
+}
 
-    let unstake_func = Func::wrap(&mut store, host::unstake);
-    linker.define("env", "unstake", unstake_func).unwrap();
+fn run_wasm(
+    blockchain: &mut Blockchain,
+    code: &[u8],
+    method: &str,
+    context: ExecutionContext,
+) -> Result<(Receipt, Option<Trace>), String> {
+    // Take the compiled module from the cache and create the store
+    let module = blockchain.modules.get_or_compile(code)?;
+    let engine = blockchain.modules.engine().clone();
+    let gas_limit = context.gas_limit;
+    let mut store: Store<ExecutionContext> = Store::new(&engine, context);
+    store.data_mut().limits = StoreLimitsBuilder::new()
+        .memory_size(static_config::MAX_CONTRACT_MEMORY_PAGES as usize * WASM_PAGE_SIZE)
+        .build();
+    store.limiter(|context| &mut context.limits);
+    store
+        .set_fuel(gas_limit)
+        .map_err(|e| format!("Failed to set gas limit: {:?}", e))?;
 
-    let debug_func = Func::wrap(&mut store, host::debug);
-    linker.define("env", "debug", debug_func).unwrap();
+    // Create a linker and define host functions
+    let mut linker: Linker<ExecutionContext> = Linker::new(&engine);
+    define_host_functions!(store, linker;
+        get_balance(lo: i32, hi: i32, user_ptr: i32, user_len: i32) -> f64;
+        transfer(lo: i32, hi: i32, to_ptr: i32, to_len: i32, amount: f64) -> i32;
+        store(lo: i32, hi: i32, key_ptr: i32, key_len: i32, value_ptr: i32, value_len: i32) -> i32;
+        load(lo: i32, hi: i32, key_ptr: i32, key_len: i32, value_ptr: i32) -> i32;
+        remove(lo: i32, hi: i32, key_ptr: i32, key_len: i32) -> i32;
+        stake(lo: i32, hi: i32, amount: f64) -> i32;
+        unstake(lo: i32, hi: i32, amount: f64) -> i32;
+        self_balance(lo: i32, hi: i32) -> f64;
+        self_address(value_ptr: i32) -> i32;
+        block_height(lo: i32, hi: i32) -> i64;
+        block_timestamp(lo: i32, hi: i32) -> i64;
+        block_epoch(lo: i32, hi: i32) -> i64;
+        block_validator(lo: i32, hi: i32, value_ptr: i32) -> i32;
+        block_hash(lo: i32, hi: i32, height: i64, value_ptr: i32) -> i32;
+        caller(value_ptr: i32) -> i32;
+        input_len() -> i32;
+        read_input(value_ptr: i32) -> i32;
+        set_return(value_ptr: i32, value_len: i32) -> i32;
+        call_contract(lo: i32, hi: i32, address_ptr: i32, address_len: i32, method_ptr: i32,
+            method_len: i32, input_ptr: i32, input_len: i32, value: f64) -> i32;
+        call_output_len() -> i32;
+        read_call_output(value_ptr: i32) -> i32;
+        emit_event(topics_ptr: i32, topics_count: i32, data_ptr: i32, data_len: i32) -> i32;
+        sha256(data_ptr: i32, data_len: i32, out_ptr: i32) -> Result<i32, Error>;
+        keccak256(data_ptr: i32, data_len: i32, out_ptr: i32) -> Result<i32, Error>;
+        ed25519_verify(public_key_ptr: i32, signature_ptr: i32, msg_ptr: i32, msg_len: i32)
+            -> Result<i32, Error>;
+        verify_merkle_proof(leaf_ptr: i32, proof_ptr: i32, proof_len: i32, root_ptr: i32)
+            -> Result<i32, Error>;
+        debug(msg_ptr: i32, msg_len: i32, value: u32) -> ();
+    );
 
     // Instantiate the module
     let instance = linker
@@ -47,25 +222,1364 @@ pub fn execute(
         .start(&mut store)
         .map_err(|e| format!("Failed to start instance: {:?}", e))?;
 
-    // Call the execute function, passing the blockchain pointer
+    // Call the requested entry point, passing the blockchain pointer
     // Split the pointer into two i32s
     let blockchain_ptr = blockchain as *mut Blockchain as usize; // Use usize to hold the full pointer
     let blockchain_ptr_low = (blockchain_ptr & 0xFFFFFFFF) as i32; // Lower 32 bits
     let blockchain_ptr_high = ((blockchain_ptr >> 32) & 0xFFFFFFFF) as i32; // Upper 32 bits
 
-    let execute = instance
-        .get_export(&store, "execute")
+    let entry_point = instance
+        .get_export(&store, method)
         .and_then(Extern::into_func)
-        .ok_or("Failed to find execute function")?;
-    let execute: Func = execute;
-    execute
-        .call(
-            &mut store,
-            &[
-                wasmi::Val::I32(blockchain_ptr_low), // Pass blockchain_ptr as an argument
-                wasmi::Val::I32(blockchain_ptr_high),
-            ],
-            &mut [wasmi::Val::I32(0)],
+        .ok_or(format!("Failed to find {} function", method))?;
+    let context = store.data_mut();
+    let depth = context.depth;
+    let contract_address = context.contract_address.clone();
+    context.record(|| TraceStep::Enter {
+        depth,
+        contract_address,
+        method: method.to_string(),
+        gas_limit,
+    });
+    let mut exit_code = [wasmi::Val::I32(0)];
+    let outcome = entry_point.call(
+        &mut store,
+        &[
+            wasmi::Val::I32(blockchain_ptr_low), // Pass blockchain_ptr as an argument
+            wasmi::Val::I32(blockchain_ptr_high),
+        ],
+        &mut exit_code,
+    );
+    let gas_used = gas_limit - store.get_fuel().unwrap_or(0);
+    let mut context = store.into_data();
+    let return_data = std::mem::take(&mut context.return_data);
+
+    let receipt = match outcome {
+        Ok(()) => {
+            let exit_code = exit_code[0].i32().unwrap_or_default();
+            // Events and payments of a failed call are discarded together
+            // with its state
+            let (events, paid_accounts) = if exit_code == 0 {
+                (
+                    std::mem::take(&mut context.events),
+                    std::mem::take(&mut context.paid_accounts),
+                )
+            } else {
+                Default::default()
+            };
+            Receipt {
+                exit_code,
+                return_data,
+                gas_used,
+                events,
+                paid_accounts,
+                ..Default::default()
+            }
+        }
+        Err(e) => Receipt {
+            exit_code: -1,
+            return_data,
+            gas_used,
+            error: Some(format!("Failed to execute contract: {:?}", e)),
+            ..Default::default()
+        },
+    };
+    context.record(|| TraceStep::Exit {
+        depth,
+        exit_code: receipt.exit_code,
+        gas_used,
+        trap: receipt.error.clone(),
+    });
+    Ok((receipt, context.trace))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bchain_error::BChainError;
+    use crate::config::static_config;
+    use crate::contracts::{address, Checkpoint};
+    use crate::message::BlockchainFacade;
+    use crate::patricia_merkle_trie::{pmt_proof, state_root};
+    use crate::primitives::{
+        BatchMode, Event, Frame, Receipt, StateEntry, TraceStep, Transaction, TransactionType, Vm,
+    };
+    use crate::tests::new_blockchain;
+    use crate::Blockchain;
+    use ed25519_dalek::{Signer, SigningKey};
+    use sha2::{Digest, Sha256};
+    use sha3::Keccak256;
+
+    // Stores the caller under "caller" and forwards half of the contract's balance to it,
+    // `pay` sends the caller the f64 amount given as input
+    const REFUND_HALF: &str = r#"
+        (module
+            (import "env" "caller" (func $caller (param i32) (result i32)))
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (import "env" "self_balance" (func $self_balance (param i32 i32) (result f64)))
+            (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "env" "transfer" (func $transfer (param i32 i32 i32 i32 f64) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "caller")
+            (func (export "execute") (param $lo i32) (param $hi i32) (result i32)
+                (local $len i32)
+                (local.set $len (call $caller (i32.const 64)))
+                (drop (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 6) (i32.const 64) (local.get $len)))
+                (call $transfer (local.get $lo) (local.get $hi) (i32.const 64) (local.get $len)
+                    (f64.div (call $self_balance (local.get $lo) (local.get $hi)) (f64.const 2))))
+            (func (export "pay") (param $lo i32) (param $hi i32) (result i32)
+                (drop (call $read_input (i32.const 128)))
+                (call $transfer (local.get $lo) (local.get $hi)
+                    (i32.const 64) (call $caller (i32.const 64)) (f64.load (i32.const 128)))))
+    "#;
+
+    // `echo` returns its input, `input_size` exits with the input length
+    const ECHO: &str = r#"
+        (module
+            (import "env" "input_len" (func $input_len (result i32)))
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "echo") (param i32 i32) (result i32)
+                (call $set_return (i32.const 0) (call $read_input (i32.const 0))))
+            (func (export "input_size") (param i32 i32) (result i32)
+                (call $input_len)))
+    "#;
+
+    // Remembers its input under "k", or writes "k" and traps when asked to explode
+    const CALLEE: &str = r#"
+        (module
+            (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "k")
+            (func (export "remember") (param $lo i32) (param $hi i32) (result i32)
+                (local $len i32)
+                (local.set $len (call $read_input (i32.const 64)))
+                (drop (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 1) (i32.const 64) (local.get $len)))
+                (call $set_return (i32.const 64) (local.get $len)))
+            (func (export "explode") (param $lo i32) (param $hi i32) (result i32)
+                (drop (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 1)))
+                unreachable))
+    "#;
+
+    // Calls the contract whose address is passed as input with 5 tokens, `recurse`
+    // keeps calling the same method until the depth limit is hit
+    const PROXY: &str = r#"
+        (module
+            (import "env" "call_contract"
+                (func $call (param i32 i32 i32 i32 i32 i32 i32 i32 f64) (result i32)))
+            (import "env" "read_call_output" (func $read_call_output (param i32) (result i32)))
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 16) "remember")
+            (data (i32.const 32) "explode")
+            (data (i32.const 48) "abc")
+            (data (i32.const 80) "recurse")
+            (func (export "forward") (param $lo i32) (param $hi i32) (result i32)
+                (local $status i32)
+                (local.set $status (call $call (local.get $lo) (local.get $hi)
+                    (i32.const 512) (call $read_input (i32.const 512)) (i32.const 16) (i32.const 8)
+                    (i32.const 48) (i32.const 3) (f64.const 5)))
+                (drop (call $set_return (i32.const 256) (call $read_call_output (i32.const 256))))
+                (local.get $status))
+            (func (export "forward_explode") (param $lo i32) (param $hi i32) (result i32)
+                (i32.store (i32.const 128) (call $call (local.get $lo) (local.get $hi)
+                    (i32.const 512) (call $read_input (i32.const 512)) (i32.const 32) (i32.const 7)
+                    (i32.const 48) (i32.const 3) (f64.const 5)))
+                (call $set_return (i32.const 128) (i32.const 4)))
+            (func (export "recurse") (param $lo i32) (param $hi i32) (result i32)
+                (local $len i32)
+                (local.set $len (call $read_input (i32.const 512)))
+                (call $call (local.get $lo) (local.get $hi)
+                    (i32.const 512) (local.get $len) (i32.const 80) (i32.const 7)
+                    (i32.const 512) (local.get $len) (f64.const 0))))
+    "#;
+
+    // `fail` writes "k" and exits with 1, `spin` never terminates
+    const FAILING: &str = r#"
+        (module
+            (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "k")
+            (func (export "fail") (param $lo i32) (param $hi i32) (result i32)
+                (drop (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 1)))
+                (i32.const 1))
+            (func (export "spin") (param $lo i32) (param $hi i32) (result i32)
+                (drop (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 1) (i32.const 0) (i32.const 1)))
+                (loop $forever (br $forever))
+                (i32.const 0)))
+    "#;
+
+    // First version of an upgradable contract, `version` exits with 1
+    const UPGRADABLE_V1: &str = r#"
+        (module
+            (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "v1")
+            (func (export "remember") (param $lo i32) (param $hi i32) (result i32)
+                (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 2) (i32.const 0) (i32.const 2)))
+            (func (export "version") (param i32 i32) (result i32)
+                (i32.const 1)))
+    "#;
+
+    // Second version, `migrate` marks the storage as migrated and `broken_migrate` fails
+    const UPGRADABLE_V2: &str = r#"
+        (module
+            (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "migrated")
+            (func (export "migrate") (param $lo i32) (param $hi i32) (result i32)
+                (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 8) (i32.const 0) (i32.const 8)))
+            (func (export "broken_migrate") (param i32 i32) (result i32)
+                (i32.const 1))
+            (func (export "version") (param i32 i32) (result i32)
+                (i32.const 2)))
+    "#;
+
+    // Emits an event with the 32-byte input as its only topic, `emit_and_fail` exits with 1
+    const EMITTER: &str = r#"
+        (module
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (import "env" "emit_event" (func $emit_event (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "hi")
+            (func $emit (export "emit") (param i32 i32) (result i32)
+                (drop (call $read_input (i32.const 64)))
+                (call $emit_event (i32.const 64) (i32.const 1) (i32.const 0) (i32.const 2)))
+            (func (export "emit_and_fail") (param $lo i32) (param $hi i32) (result i32)
+                (drop (call $emit (local.get $lo) (local.get $hi)))
+                (i32.const 1)))
+    "#;
+
+    // Hashes return the digest of the input, checks exit with the precompile's result
+    const PRECOMPILES: &str = r#"
+        (module
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+            (import "env" "sha256" (func $sha256 (param i32 i32 i32) (result i32)))
+            (import "env" "keccak256" (func $keccak256 (param i32 i32 i32) (result i32)))
+            (import "env" "ed25519_verify" (func $ed25519_verify (param i32 i32 i32 i32) (result i32)))
+            (import "env" "verify_merkle_proof" (func $verify_merkle_proof (param i32 i32 i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "sha256") (param i32 i32) (result i32)
+                (drop (call $sha256 (i32.const 1024) (call $read_input (i32.const 1024)) (i32.const 0)))
+                (call $set_return (i32.const 0) (i32.const 32)))
+            (func (export "keccak256") (param i32 i32) (result i32)
+                (drop (call $keccak256 (i32.const 1024) (call $read_input (i32.const 1024)) (i32.const 0)))
+                (call $set_return (i32.const 0) (i32.const 32)))
+            ;; Input: public key, signature, message
+            (func (export "ed25519_verify") (param i32 i32) (result i32)
+                (local $len i32)
+                (local.set $len (call $read_input (i32.const 1024)))
+                (call $ed25519_verify (i32.const 1024) (i32.const 1056) (i32.const 1120)
+                    (i32.sub (local.get $len) (i32.const 96))))
+            ;; Input: root, leaf, proof steps
+            (func (export "verify_merkle_proof") (param i32 i32) (result i32)
+                (local $len i32)
+                (local.set $len (call $read_input (i32.const 1024)))
+                (call $verify_merkle_proof (i32.const 1056) (i32.const 1088)
+                    (i32.div_u (i32.sub (local.get $len) (i32.const 64)) (i32.const 33))
+                    (i32.const 1024))))
+    "#;
+
+    // Returns height, timestamp, epoch, the previous block hash and the validator
+    const BLOCK_INFO: &str = r#"
+        (module
+            (import "env" "block_height" (func $height (param i32 i32) (result i64)))
+            (import "env" "block_timestamp" (func $timestamp (param i32 i32) (result i64)))
+            (import "env" "block_epoch" (func $epoch (param i32 i32) (result i64)))
+            (import "env" "block_validator" (func $validator (param i32 i32 i32) (result i32)))
+            (import "env" "block_hash" (func $hash (param i32 i32 i64 i32) (result i32)))
+            (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "info") (param $lo i32) (param $hi i32) (result i32)
+                (i64.store (i32.const 0) (call $height (local.get $lo) (local.get $hi)))
+                (i64.store (i32.const 8) (call $timestamp (local.get $lo) (local.get $hi)))
+                (i64.store (i32.const 16) (call $epoch (local.get $lo) (local.get $hi)))
+                (drop (call $hash (local.get $lo) (local.get $hi)
+                    (i64.sub (i64.load (i32.const 0)) (i64.const 1)) (i32.const 24)))
+                (call $set_return (i32.const 0)
+                    (i32.add (i32.const 56) (call $validator (local.get $lo) (local.get $hi) (i32.const 56)))))
+            (func (export "current_hash") (param $lo i32) (param $hi i32) (result i32)
+                (call $hash (local.get $lo) (local.get $hi)
+                    (call $height (local.get $lo) (local.get $hi)) (i32.const 0))))
+    "#;
+
+    // `grow` exits with the result of growing memory by the number of pages in the input
+    const GROWER: &str = r#"
+        (module
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (func (export "grow") (param i32 i32) (result i32)
+                (drop (call $read_input (i32.const 0)))
+                (memory.grow (i32.load (i32.const 0)))))
+    "#;
+
+    // `put` stores its input under "k" and `delete` removes it
+    const DEPOSITOR: &str = r#"
+        (module
+            (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+            (import "env" "remove" (func $remove (param i32 i32 i32 i32) (result i32)))
+            (import "env" "read_input" (func $read_input (param i32) (result i32)))
+            (memory (export "memory") 1)
+            (data (i32.const 0) "k")
+            (func (export "put") (param $lo i32) (param $hi i32) (result i32)
+                (call $store (local.get $lo) (local.get $hi)
+                    (i32.const 0) (i32.const 1) (i32.const 64) (call $read_input (i32.const 64))))
+            (func (export "delete") (param $lo i32) (param $hi i32) (result i32)
+                (call $remove (local.get $lo) (local.get $hi) (i32.const 0) (i32.const 1))))
+    "#;
+
+    // EVM counter: increments slot 0, logs the new count as a topic and returns it
+    const EVM_COUNTER: [u8; 26] = [
+        0x60, 0x00, 0x54, 0x60, 0x01, 0x01, 0x80, 0x60, 0x00, 0x55, 0x80, 0x60, 0x00, 0x52, 0x80,
+        0x60, 0x20, 0x60, 0x00, 0xa1, 0x50, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ];
+
+    // EVM code that writes slot 0 and reverts
+    const EVM_REVERTER: [u8; 10] = [0x60, 0x01, 0x60, 0x00, 0x55, 0x60, 0x00, 0x60, 0x00, 0xfd];
+
+    // EVM code that calls the sha256 precompile and returns whether it succeeded
+    const EVM_PRECOMPILE_CALLER: [u8; 22] = [
+        0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x00, 0x60, 0x02, 0x5a, 0xf1, 0x60,
+        0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ];
+
+    /// EVM creation code returning `runtime` as the contract's code
+    fn evm_init(runtime: &[u8]) -> Vec<u8> {
+        let len = runtime.len() as u8;
+        let mut code = vec![
+            0x60, len, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, len, 0x60, 0x00, 0xf3,
+        ];
+        code.extend_from_slice(runtime);
+        code
+    }
+
+    /// Includes a single transaction in a new block and returns its receipt
+    fn submit(
+        blockchain: &mut Blockchain,
+        sender: &str,
+        tx_type: TransactionType,
+        fee: f64,
+    ) -> Receipt {
+        blockchain
+            .add_block(vec![Transaction::new(sender.to_string(), tx_type, fee)])
+            .unwrap();
+        blockchain.get_receipts(blockchain.chain.len() - 1).unwrap()[0].clone()
+    }
+
+    fn fund(blockchain: &mut Blockchain, user: &str, amount: f64) {
+        submit(
+            blockchain,
+            static_config::GENESIS,
+            TransactionType::Transfer {
+                sender: static_config::GENESIS.to_string(),
+                receiver: user.to_string(),
+                amount,
+            },
+            0.0,
+        );
+    }
+
+    fn deploy(blockchain: &mut Blockchain, deployer: &str, wat: &str) -> String {
+        let code = wat::parse_str(wat).unwrap();
+        let receipt = submit(
+            blockchain,
+            deployer,
+            TransactionType::DeployContract {
+                code,
+                salt: None,
+                vm: Vm::Wasm,
+            },
+            0.0,
+        );
+        receipt.contract_address.unwrap()
+    }
+
+    fn call(
+        blockchain: &mut Blockchain,
+        sender: &str,
+        contract_address: &str,
+        method: &str,
+        input: &[u8],
+        value: f64,
+        fee: f64,
+    ) -> Receipt {
+        submit(
+            blockchain,
+            sender,
+            TransactionType::CallContract {
+                contract_address: contract_address.to_string(),
+                value,
+                method: method.to_string(),
+                input: input.to_vec(),
+            },
+            fee,
         )
-        .map_err(|e| format!("Failed to execute contract: {:?}", e))
+    }
+
+    #[test]
+    fn test_call_value_is_credited_to_contract_account() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 100.0);
+        let contract = deploy(&mut blockchain, "Alice", REFUND_HALF);
+        assert_eq!(blockchain.wallets.wallets[&contract].balance, 0.0);
+
+        let receipt = call(
+            &mut blockchain,
+            "Alice",
+            &contract,
+            "execute",
+            &[],
+            40.0,
+            1.0,
+        );
+
+        assert_eq!(receipt.exit_code, 0);
+        // "caller" and "Alice" take 11 bytes of storage paid from the contract's balance
+        let refund = (40.0 - 11.0 * static_config::STORAGE_DEPOSIT_PER_BYTE) / 2.0;
+        assert_eq!(blockchain.wallets.wallets[&contract].balance, refund);
+        assert_eq!(blockchain.wallets.wallets["Alice"].balance, 59.0 + refund);
+        assert_eq!(
+            blockchain.contract_storage[&contract]["caller"],
+            b"Alice".to_vec()
+        );
+
+        // Contracts pay only from their own balance, and only amounts it covers
+        for amount in [1e6, -1.0, f64::NAN, f64::INFINITY] {
+            let input = amount.to_le_bytes();
+            let receipt = call(&mut blockchain, "Alice", &contract, "pay", &input, 0.0, 0.0);
+            assert_eq!(receipt.exit_code, 1);
+        }
+        assert_eq!(blockchain.wallets.wallets[&contract].balance, refund);
+        assert_eq!(blockchain.wallets.wallets["Alice"].balance, 59.0 + refund);
+        let receipt = call(
+            &mut blockchain,
+            "Alice",
+            &contract,
+            "pay",
+            &refund.to_le_bytes(),
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(blockchain.wallets.wallets[&contract].balance, 0.0);
+    }
+
+    #[test]
+    fn test_call_method_input_and_return_data_end_up_in_receipt() {
+        let mut blockchain = new_blockchain();
+        let contract = deploy(&mut blockchain, static_config::GENESIS, ECHO);
+
+        let call = |method: &str| {
+            Transaction::new(
+                static_config::GENESIS.to_string(),
+                TransactionType::CallContract {
+                    contract_address: contract.clone(),
+                    value: 0.0,
+                    method: method.to_string(),
+                    input: b"hello".to_vec(),
+                },
+                0.0,
+            )
+        };
+        blockchain
+            .add_block(vec![call("echo"), call("input_size")])
+            .unwrap();
+
+        let receipts = blockchain.get_receipts(2).unwrap();
+        assert_eq!(receipts[0].exit_code, 0);
+        assert_eq!(receipts[0].return_data, b"hello".to_vec());
+        assert_eq!(receipts[1].exit_code, 5);
+        assert!(receipts[1].return_data.is_empty());
+
+        blockchain.add_block(vec![call("missing")]).unwrap();
+        let receipts = blockchain.get_receipts(3).unwrap();
+        assert_eq!(receipts[0].exit_code, -1);
+        assert!(receipts[0].error.is_some());
+    }
+
+    #[test]
+    fn test_nested_call_passes_value_and_reverts_failed_callee() {
+        let mut blockchain = new_blockchain();
+        let callee = deploy(&mut blockchain, static_config::GENESIS, CALLEE);
+        let proxy = deploy(&mut blockchain, static_config::GENESIS, PROXY);
+        let genesis = static_config::GENESIS;
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &proxy,
+            "forward",
+            callee.as_bytes(),
+            10.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(receipt.return_data, b"abc".to_vec());
+        assert_eq!(blockchain.contract_storage[&callee]["k"], b"abc".to_vec());
+        let callee_balance = 5.0 - 4.0 * static_config::STORAGE_DEPOSIT_PER_BYTE;
+        assert_eq!(blockchain.wallets.wallets[&callee].balance, callee_balance);
+        assert_eq!(blockchain.wallets.wallets[&proxy].balance, 5.0);
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &proxy,
+            "forward_explode",
+            callee.as_bytes(),
+            10.0,
+            0.0,
+        );
+        assert_eq!(receipt.return_data, (-1i32).to_le_bytes().to_vec());
+        assert_eq!(blockchain.contract_storage[&callee]["k"], b"abc".to_vec());
+        assert_eq!(blockchain.wallets.wallets[&callee].balance, callee_balance);
+        assert_eq!(blockchain.wallets.wallets[&proxy].balance, 15.0);
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &proxy,
+            "recurse",
+            proxy.as_bytes(),
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, -1);
+    }
+
+    #[test]
+    fn test_host_functions_reject_out_of_bounds_memory() {
+        // Each check returns its number when a host function accepts bad arguments
+        const PROBE: &str = r#"
+            (module
+                (import "env" "call_contract" (func $call
+                    (param i32 i32 i32 i32 i32 i32 i32 i32 f64) (result i32)))
+                (import "env" "read_input" (func $read_input (param i32) (result i32)))
+                (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+                (import "env" "emit_event" (func $emit_event (param i32 i32 i32 i32) (result i32)))
+                (import "env" "sha256" (func $sha256 (param i32 i32 i32) (result i32)))
+                (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+                (import "env" "load" (func $load (param i32 i32 i32 i32 i32) (result i32)))
+                (import "env" "remove" (func $remove (param i32 i32 i32 i32) (result i32)))
+                (import "env" "verify_merkle_proof" (func $verify_merkle_proof
+                    (param i32 i32 i32 i32) (result i32)))
+                (memory (export "memory") 1)
+                (data (i32.const 0) "echo")
+                (func (export "probe") (param $lo i32) (param $hi i32) (result i32)
+                    (local $len i32)
+                    (local.set $len (call $read_input (i32.const 64)))
+                    (if (i32.ne (i32.const -1) (call $call (local.get $lo) (local.get $hi)
+                            (i32.const 65530) (i32.const 100) (i32.const 0) (i32.const 4)
+                            (i32.const 0) (i32.const 0) (f64.const 0)))
+                        (then (return (i32.const 1))))
+                    (if (i32.ne (i32.const -1) (call $call (local.get $lo) (local.get $hi)
+                            (i32.const 2147483647) (i32.const 2147483647) (i32.const 0) (i32.const 4)
+                            (i32.const 0) (i32.const 0) (f64.const 0)))
+                        (then (return (i32.const 2))))
+                    (if (i32.ne (i32.const -1) (call $call (local.get $lo) (local.get $hi)
+                            (i32.const 64) (local.get $len) (i32.const 0) (i32.const 4)
+                            (i32.const 0) (i32.const 0) (f64.const nan)))
+                        (then (return (i32.const 3))))
+                    (if (i32.ne (i32.const -1) (call $read_input (i32.const -1)))
+                        (then (return (i32.const 4))))
+                    (if (i32.ne (i32.const 1) (call $set_return (i32.const -8) (i32.const 4)))
+                        (then (return (i32.const 5))))
+                    (if (i32.ne (i32.const 1) (call $emit_event (i32.const 0) (i32.const 1)
+                            (i32.const 2147483647) (i32.const 2147483647)))
+                        (then (return (i32.const 6))))
+                    (if (i32.ne (i32.const 1) (call $sha256 (i32.const -1) (i32.const 4) (i32.const 0)))
+                        (then (return (i32.const 7))))
+                    (if (i32.ne (i32.const 1) (call $store (local.get $lo) (local.get $hi)
+                            (i32.const 2147483647) (i32.const 2147483647) (i32.const 0) (i32.const 4)))
+                        (then (return (i32.const 8))))
+                    (if (i32.ne (i32.const 1) (call $store (local.get $lo) (local.get $hi)
+                            (i32.const 0) (i32.const 4) (i32.const -1) (i32.const 4)))
+                        (then (return (i32.const 9))))
+                    (if (i32.ne (i32.const 0) (call $store (local.get $lo) (local.get $hi)
+                            (i32.const 0) (i32.const 4) (i32.const 0) (i32.const 4)))
+                        (then (return (i32.const 10))))
+                    (if (i32.ne (i32.const -1) (call $load (local.get $lo) (local.get $hi)
+                            (i32.const 0) (i32.const 4) (i32.const 65534)))
+                        (then (return (i32.const 11))))
+                    (if (i32.ne (i32.const 1) (call $remove (local.get $lo) (local.get $hi)
+                            (i32.const -1) (i32.const 4)))
+                        (then (return (i32.const 12))))
+                    (if (i32.ne (i32.const 1) (call $verify_merkle_proof
+                            (i32.const 0) (i32.const 0) (i32.const -1) (i32.const 0)))
+                        (then (return (i32.const 13))))
+                    (if (i32.ne (i32.const 1) (call $verify_merkle_proof
+                            (i32.const 0) (i32.const 0) (i32.const 2147483647) (i32.const 0)))
+                        (then (return (i32.const 14))))
+                    (call $call (local.get $lo) (local.get $hi) (i32.const 64) (local.get $len)
+                        (i32.const 0) (i32.const 4) (i32.const 0) (i32.const 0) (f64.const 0))))
+        "#;
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let echo = deploy(&mut blockchain, genesis, ECHO);
+        let probe = deploy(&mut blockchain, genesis, PROBE);
+        // The call value pays the deposit of the one valid store
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &probe,
+            "probe",
+            echo.as_bytes(),
+            1.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 0);
+    }
+
+    #[test]
+    fn test_failed_call_reverts_state_but_keeps_fee() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 100.0);
+        let contract = deploy(&mut blockchain, static_config::GENESIS, FAILING);
+
+        for (method, exit_code, alice_balance) in [("fail", 1, 99.0), ("spin", -1, 98.0)] {
+            let receipt = call(&mut blockchain, "Alice", &contract, method, &[], 10.0, 1.0);
+
+            assert_eq!(receipt.exit_code, exit_code);
+            assert!(!blockchain.contract_storage.contains_key(&contract));
+            assert_eq!(blockchain.wallets.wallets[&contract].balance, 0.0);
+            assert_eq!(blockchain.wallets.wallets["Alice"].balance, alice_balance);
+        }
+        let out_of_gas = &blockchain.get_receipts(4).unwrap()[0];
+        assert_eq!(out_of_gas.gas_used, static_config::CONTRACT_GAS_LIMIT);
+        assert!(out_of_gas.error.as_ref().unwrap().contains("OutOfFuel"));
+    }
+
+    #[test]
+    fn test_checkpoints_undo_only_changes_made_after_them() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 100.0);
+
+        let outer = Checkpoint::new(&mut blockchain);
+        blockchain.write_storage("contract", "k", Some(b"outer".to_vec()));
+        let inner = Checkpoint::new(&mut blockchain);
+        blockchain.write_storage("contract", "k", Some(b"inner".to_vec()));
+        let transfer = blockchain.wallets.transfer("Alice", "Bob", 10.0, 0.0);
+        assert!(transfer.is_ok());
+        inner.restore(&mut blockchain);
+        assert_eq!(
+            blockchain.contract_storage["contract"]["k"],
+            b"outer".to_vec()
+        );
+        assert!(!blockchain.wallets.wallets.contains_key("Bob"));
+
+        // Changes of a checkpoint that is dropped are undone by the outer one
+        let kept = Checkpoint::new(&mut blockchain);
+        blockchain.wallets.get_or_create("Bob").balance += 1.0;
+        drop(kept);
+        outer.restore(&mut blockchain);
+        assert!(!blockchain.contract_storage.contains_key("contract"));
+        assert!(!blockchain.wallets.wallets.contains_key("Bob"));
+        assert_eq!(blockchain.wallets.wallets["Alice"].balance, 100.0);
+
+        // Without a checkpoint nothing is journaled
+        blockchain.write_storage("contract", "k", Some(b"v".to_vec()));
+        assert_eq!(
+            format!("{:?}", blockchain.storage_journal),
+            "Journal { changes: 0 }"
+        );
+    }
+
+    #[test]
+    fn test_contract_addresses_are_derived_from_deployer() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 100.0);
+        let first = deploy(&mut blockchain, "Alice", ECHO);
+        let second = deploy(&mut blockchain, "Alice", ECHO);
+        assert_eq!(first, address::derive("Alice", 0));
+        assert_eq!(second, address::derive("Alice", 1));
+        assert_eq!(blockchain.wallets.wallets["Alice"].nonce, 2);
+
+        let code = wat::parse_str(ECHO).unwrap();
+        let salted = address::derive_salted("Alice", b"salt", &code);
+        let deploy_salted = TransactionType::DeployContract {
+            code,
+            salt: Some(b"salt".to_vec()),
+            vm: Vm::Wasm,
+        };
+        let receipt = submit(&mut blockchain, "Alice", deploy_salted.clone(), 0.0);
+        assert_eq!(receipt.contract_address, Some(salted));
+        assert!(blockchain
+            .add_block(vec![Transaction::new(
+                "Alice".to_string(),
+                deploy_salted,
+                0.0
+            )])
+            .is_err());
+
+        let no_memory = TransactionType::DeployContract {
+            code: wat::parse_str("(module)").unwrap(),
+            salt: None,
+            vm: Vm::Wasm,
+        };
+        assert!(blockchain
+            .add_block(vec![Transaction::new("Alice".to_string(), no_memory, 0.0)])
+            .is_err());
+        assert_eq!(blockchain.contracts.len(), 3);
+    }
+
+    #[test]
+    fn test_contract_upgrade_keeps_storage_and_destroy_refunds_admin() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 100.0);
+        fund(&mut blockchain, "Bob", 100.0);
+        let contract = deploy(&mut blockchain, "Alice", UPGRADABLE_V1);
+        call(
+            &mut blockchain,
+            "Bob",
+            &contract,
+            "remember",
+            &[],
+            30.0,
+            0.0,
+        );
+        let upgrade = |sender: &str, migrate: &str| {
+            Transaction::new(
+                sender.to_string(),
+                TransactionType::UpgradeContract {
+                    contract_address: contract.clone(),
+                    code: wat::parse_str(UPGRADABLE_V2).unwrap(),
+                    migrate: Some(migrate.to_string()),
+                },
+                0.0,
+            )
+        };
+
+        assert!(blockchain
+            .add_block(vec![upgrade("Bob", "migrate")])
+            .is_err());
+        blockchain
+            .add_block(vec![upgrade("Alice", "broken_migrate")])
+            .unwrap();
+        assert_eq!(blockchain.get_receipts(5).unwrap()[0].exit_code, 1);
+        let receipt = call(&mut blockchain, "Bob", &contract, "version", &[], 0.0, 0.0);
+        assert_eq!(receipt.exit_code, 1);
+
+        blockchain
+            .add_block(vec![upgrade("Alice", "migrate")])
+            .unwrap();
+        let receipt = &blockchain.get_receipts(7).unwrap()[0];
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(receipt.contract_address.as_ref(), Some(&contract));
+        let receipt = call(&mut blockchain, "Bob", &contract, "version", &[], 0.0, 0.0);
+        assert_eq!(receipt.exit_code, 2);
+        assert_eq!(blockchain.contract_storage[&contract]["v1"], b"v1".to_vec());
+        assert_eq!(
+            blockchain.contract_storage[&contract]["migrated"],
+            b"migrated".to_vec()
+        );
+
+        let destroy = TransactionType::DestroyContract {
+            contract_address: contract.clone(),
+        };
+        submit(&mut blockchain, "Alice", destroy, 0.0);
+        assert_eq!(blockchain.wallets.wallets["Alice"].balance, 130.0);
+        assert!(!blockchain.wallets.wallets.contains_key(&contract));
+        assert!(!blockchain.contract_storage.contains_key(&contract));
+        assert!(!blockchain.contracts.contains_key(&contract));
+    }
+
+    #[test]
+    fn test_events_are_queryable_by_contract_and_topic() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let emitter = deploy(&mut blockchain, genesis, EMITTER);
+        let other = deploy(&mut blockchain, genesis, EMITTER);
+        let (topic_a, topic_b) = ([0xaa; 32], [0xbb; 32]);
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &emitter,
+            "emit",
+            &topic_a,
+            0.0,
+            0.0,
+        );
+        assert_eq!(
+            receipt.events,
+            vec![Event {
+                contract_address: emitter.clone(),
+                topics: vec![topic_a],
+                data: b"hi".to_vec(),
+            }]
+        );
+        call(
+            &mut blockchain,
+            genesis,
+            &emitter,
+            "emit",
+            &topic_b,
+            0.0,
+            0.0,
+        );
+        call(&mut blockchain, genesis, &other, "emit", &topic_a, 0.0, 0.0);
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &emitter,
+            "emit_and_fail",
+            &topic_a,
+            0.0,
+            0.0,
+        );
+        assert!(receipt.events.is_empty());
+
+        let heights = |topic: Option<&[u8; 32]>, from: usize, to: usize| {
+            blockchain
+                .get_events(&emitter, topic, from, to)
+                .into_iter()
+                .map(|(height, _)| height)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(heights(None, 0, 100), vec![3, 4]);
+        assert_eq!(heights(Some(&topic_a), 0, 100), vec![3]);
+        assert_eq!(heights(Some(&topic_b), 0, 3), Vec::<usize>::new());
+        assert!(!blockchain
+            .chain
+            .get_block_by_idx(1)
+            .unwrap()
+            .logs_bloom
+            .contains(emitter.as_bytes()));
+    }
+
+    #[test]
+    fn test_storage_deposit_is_locked_and_released() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let contract = deploy(&mut blockchain, genesis, DEPOSITOR);
+        let wallet = |blockchain: &Blockchain| {
+            let wallet = &blockchain.wallets.wallets[&contract];
+            (wallet.balance, wallet.storage_deposit)
+        };
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &contract,
+            "put",
+            &[0; 99],
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 1);
+        assert!(!blockchain.contract_storage.contains_key(&contract));
+
+        call(
+            &mut blockchain,
+            genesis,
+            &contract,
+            "put",
+            &[0; 99],
+            2.0,
+            0.0,
+        );
+        assert_eq!(wallet(&blockchain), (1.0, 1.0));
+        call(
+            &mut blockchain,
+            genesis,
+            &contract,
+            "put",
+            &[0; 49],
+            0.0,
+            0.0,
+        );
+        assert_eq!(wallet(&blockchain), (1.5, 0.5));
+
+        let receipt = call(&mut blockchain, genesis, &contract, "delete", &[], 0.0, 0.0);
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(wallet(&blockchain), (2.0, 0.0));
+        assert!(blockchain.contract_storage[&contract].is_empty());
+        let receipt = call(&mut blockchain, genesis, &contract, "delete", &[], 0.0, 0.0);
+        assert_eq!(receipt.exit_code, 1);
+    }
+
+    #[test]
+    fn test_past_storage_values_come_with_proofs() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let contract = deploy(&mut blockchain, genesis, DEPOSITOR);
+        for value in [b"one".as_slice(), b"two"] {
+            call(&mut blockchain, genesis, &contract, "put", value, 1.0, 0.0);
+        }
+        call(&mut blockchain, genesis, &contract, "delete", &[], 0.0, 0.0);
+
+        for (height, expected) in [(2, b"one"), (3, b"two")] {
+            let (value, proof) = blockchain
+                .get_contract_storage_at(&contract, "k", height)
+                .unwrap();
+            assert_eq!(value, expected.to_vec());
+            let entry = StateEntry::Storage {
+                address: contract.clone(),
+                key: "k".to_string(),
+                value,
+            };
+            let block = blockchain.chain.get_block_by_idx(height).unwrap();
+            assert!(proof.verify(&entry, &block.state_root));
+        }
+        for height in [1, 4] {
+            assert!(matches!(
+                blockchain.get_contract_storage_at(&contract, "k", height),
+                Err(BChainError::StorageNotFound(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_failed_batch_reverts_deployment() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 10.0);
+        let deploy_depositor = TransactionType::DeployContract {
+            code: wat::parse_str(DEPOSITOR).unwrap(),
+            salt: None,
+            vm: Vm::Wasm,
+        };
+        let overdraft = TransactionType::Transfer {
+            sender: "Alice".to_string(),
+            receiver: "Bob".to_string(),
+            amount: 20.0,
+        };
+        let batch = |mode| TransactionType::Batch {
+            operations: vec![deploy_depositor.clone(), overdraft.clone()],
+            mode,
+        };
+
+        let receipt = submit(
+            &mut blockchain,
+            "Alice",
+            batch(BatchMode::AllOrNothing),
+            1.0,
+        );
+        assert_eq!(receipt.exit_code, -1);
+        let contract = receipt.operations[0].contract_address.clone().unwrap();
+        assert!(!blockchain.contracts.contains_key(&contract));
+        assert_eq!(blockchain.wallets.wallets["Alice"].nonce, 0);
+        assert_eq!(blockchain.wallets.wallets["Alice"].balance, 9.0);
+
+        let receipt = submit(&mut blockchain, "Alice", batch(BatchMode::BestEffort), 1.0);
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(
+            receipt.operations[0].contract_address,
+            Some(contract.clone())
+        );
+        assert!(blockchain.contracts.contains_key(&contract));
+        assert_eq!(
+            blockchain.get_account_history(&contract, 0, 10),
+            &blockchain.get_account_history("Alice", 0, 10)[1..]
+        );
+    }
+
+    #[test]
+    fn test_query_returns_output_without_changing_state() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let echo = deploy(&mut blockchain, genesis, ECHO);
+        let depositor = deploy(&mut blockchain, genesis, DEPOSITOR);
+        call(&mut blockchain, genesis, &depositor, "put", b"v", 1.0, 0.0);
+        let height = blockchain.get_height();
+        let genesis_balance = blockchain.wallets.wallets[genesis].balance;
+
+        let receipt = blockchain.query_contract(&echo, "echo", b"hi").unwrap();
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(receipt.return_data, b"hi".to_vec());
+        assert!(receipt.gas_used > 0);
+
+        let receipt = blockchain
+            .query_contract(&depositor, "put", b"changed")
+            .unwrap();
+        assert_eq!(receipt.exit_code, 1);
+        let receipt = blockchain
+            .query_contract(&depositor, "delete", b"")
+            .unwrap();
+        assert_eq!(receipt.exit_code, 1);
+        assert_eq!(blockchain.contract_storage[&depositor]["k"], b"v".to_vec());
+
+        assert!(matches!(
+            blockchain.query_contract("missing", "echo", b""),
+            Err(BChainError::ContractNotFound(_))
+        ));
+        assert_eq!(blockchain.get_height(), height);
+        assert_eq!(blockchain.wallets.wallets[genesis].balance, genesis_balance);
+    }
+
+    #[test]
+    fn test_precompiles_hash_and_verify() {
+        let mut blockchain = new_blockchain();
+        let contract = deploy(&mut blockchain, static_config::GENESIS, PRECOMPILES);
+
+        let receipt = blockchain
+            .query_contract(&contract, "sha256", b"abc")
+            .unwrap();
+        assert_eq!(receipt.return_data, Sha256::digest(b"abc").to_vec());
+        assert!(receipt.gas_used > static_config::HASH_GAS + static_config::HASH_WORD_GAS);
+        let receipt = blockchain
+            .query_contract(&contract, "keccak256", b"abc")
+            .unwrap();
+        assert_eq!(receipt.return_data, Keccak256::digest(b"abc").to_vec());
+
+        let key = SigningKey::from_bytes(&[7; 32]);
+        let signed = |msg: &[u8], signature: &[u8]| {
+            [key.verifying_key().as_bytes(), signature, msg].concat()
+        };
+        let signature = key.sign(b"transfer 10").to_bytes();
+        let input = signed(b"transfer 10", &signature);
+        let receipt = blockchain
+            .query_contract(&contract, "ed25519_verify", &input)
+            .unwrap();
+        assert_eq!(receipt.exit_code, 0);
+        assert!(receipt.gas_used > static_config::ED25519_VERIFY_GAS);
+        let input = signed(b"transfer 99", &signature);
+        let receipt = blockchain
+            .query_contract(&contract, "ed25519_verify", &input)
+            .unwrap();
+        assert_eq!(receipt.exit_code, 1);
+
+        // Proves the genesis wallet against the state root
+        let entries = blockchain.state_entries();
+        let (root, _) = state_root::compute(&entries).unwrap();
+        let entry = StateEntry::Wallet {
+            user: static_config::GENESIS.to_string(),
+            wallet: blockchain.wallets.wallets[static_config::GENESIS].clone(),
+        };
+        let leaf = state_root::leaf_hash(&entry).unwrap();
+        let proof = pmt_proof::get_merkle_proof(&entries, &entry).unwrap();
+        let mut input = [root.0, leaf.0].concat();
+        for (sibling, is_left) in &proof {
+            input.extend(sibling.0);
+            input.push(*is_left as u8);
+        }
+        let receipt = blockchain
+            .query_contract(&contract, "verify_merkle_proof", &input)
+            .unwrap();
+        assert_eq!(receipt.exit_code, 0);
+        input[0] ^= 1;
+        let receipt = blockchain
+            .query_contract(&contract, "verify_merkle_proof", &input)
+            .unwrap();
+        assert_eq!(receipt.exit_code, 1);
+    }
+
+    #[test]
+    fn test_contract_sees_block_context() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let contract = deploy(&mut blockchain, genesis, BLOCK_INFO);
+        for _ in 0..static_config::EPOCH_HEIGHT {
+            blockchain.add_block(vec![]).unwrap();
+        }
+
+        let receipt = call(&mut blockchain, genesis, &contract, "info", b"", 0.0, 0.0);
+        assert_eq!(receipt.exit_code, 0);
+        let height = blockchain.get_height();
+        let block = blockchain.chain.get_block_by_idx(height).unwrap();
+        let field =
+            |i: usize| i64::from_le_bytes(receipt.return_data[i..i + 8].try_into().unwrap());
+        assert_eq!(field(0), height as i64);
+        assert_eq!(field(8), block.timestamp);
+        assert_eq!(field(16), 1);
+        assert_eq!(receipt.return_data[24..56], block.previous_hash.0);
+        assert_eq!(&receipt.return_data[56..], block.validator.as_bytes());
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &contract,
+            "current_hash",
+            b"",
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 1);
+    }
+
+    #[test]
+    fn test_validate_rejects_nondeterministic_modules() {
+        let validate = |wat: &str| super::validate(&wat::parse_str(wat).unwrap());
+        assert!(validate(REFUND_HALF).is_ok());
+
+        let rejected = [
+            // f32 arithmetic
+            r#"(module (memory (export "memory") 1)
+                (func (export "run") (param i32 i32) (result i32)
+                    (i32.trunc_f32_s (f32.add (f32.const 1) (f32.const 2)))))"#,
+            // Too many memory pages
+            r#"(module (memory (export "memory") 33)
+                (func (export "run") (param i32 i32) (result i32) (i32.const 0)))"#,
+            // Shared memory
+            r#"(module (memory (export "memory") 1 1 shared)
+                (func (export "run") (param i32 i32) (result i32) (i32.const 0)))"#,
+            // SIMD
+            r#"(module (memory (export "memory") 1)
+                (func (export "run") (param i32 i32) (result i32)
+                    (i32x4.extract_lane 0 (v128.const i32x4 0 0 0 0))))"#,
+            // Imported memory
+            r#"(module (import "env" "memory" (memory 1)) (export "memory" (memory 0))
+                (func (export "run") (param i32 i32) (result i32) (i32.const 0)))"#,
+            // Import from outside the host
+            r#"(module (import "wasi" "exit" (func (param i32)))
+                (memory (export "memory") 1)
+                (func (export "run") (param i32 i32) (result i32) (i32.const 0)))"#,
+            // No memory export
+            r#"(module (memory 1)
+                (func (export "run") (param i32 i32) (result i32) (i32.const 0)))"#,
+            // No methods
+            r#"(module (memory (export "memory") 1))"#,
+        ];
+        for wat in rejected {
+            assert!(validate(wat).is_err(), "accepted {}", wat);
+        }
+        assert!(super::validate(&vec![0; static_config::MAX_CONTRACT_CODE_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn test_memory_growth_is_limited() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let contract = deploy(&mut blockchain, genesis, GROWER);
+
+        // Every call starts with the single page the module declares
+        let pages = static_config::MAX_CONTRACT_MEMORY_PAGES as u32 - 1;
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &contract,
+            "grow",
+            &pages.to_le_bytes(),
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 1);
+
+        let receipt = call(
+            &mut blockchain,
+            genesis,
+            &contract,
+            "grow",
+            &(pages + 1).to_le_bytes(),
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, -1);
+    }
+
+    #[test]
+    fn test_trace_replays_call_against_historical_state() {
+        let mut blockchain = new_blockchain();
+        let genesis = static_config::GENESIS;
+        let callee = deploy(&mut blockchain, genesis, CALLEE);
+        let proxy = deploy(&mut blockchain, genesis, PROXY);
+        let forward = |blockchain: &mut Blockchain, method: &str| {
+            call(
+                blockchain,
+                genesis,
+                &proxy,
+                method,
+                callee.as_bytes(),
+                10.0,
+                0.0,
+            )
+        };
+        forward(&mut blockchain, "forward");
+        let first_height = blockchain.get_height();
+        forward(&mut blockchain, "forward_explode");
+        let explode_height = blockchain.get_height();
+        // Changes the state the traced blocks started from
+        blockchain.add_block(vec![]).unwrap();
+
+        let trace = blockchain.trace_transaction(first_height, 0).unwrap();
+        assert!(trace.steps.contains(&TraceStep::StorageWrite {
+            depth: 1,
+            contract_address: callee.clone(),
+            key: "k".to_string(),
+            before: None,
+            after: Some(b"abc".to_vec()),
+        }));
+        assert!(trace.backtrace().is_empty());
+
+        let trace = blockchain.trace_transaction(explode_height, 0).unwrap();
+        assert!(trace.steps.contains(&TraceStep::StorageWrite {
+            depth: 1,
+            contract_address: callee.clone(),
+            key: "k".to_string(),
+            before: Some(b"abc".to_vec()),
+            after: Some(b"k".to_vec()),
+        }));
+        assert!(trace.steps.iter().any(|step| matches!(
+            step,
+            TraceStep::HostCall { depth: 0, name, result, .. }
+                if name == "call_contract" && result == "-1"
+        )));
+        assert!(matches!(
+            trace.steps.last(),
+            Some(TraceStep::Exit {
+                depth: 0,
+                exit_code: 0,
+                trap: None,
+                ..
+            })
+        ));
+        assert_eq!(
+            trace.backtrace(),
+            vec![
+                Frame {
+                    contract_address: proxy.clone(),
+                    method: "forward_explode".to_string(),
+                },
+                Frame {
+                    contract_address: callee.clone(),
+                    method: "explode".to_string(),
+                },
+            ]
+        );
+
+        assert!(matches!(
+            blockchain.trace_transaction(explode_height, 1),
+            Err(BChainError::TransactionNotFound(_, 1))
+        ));
+    }
+
+    #[test]
+    fn test_evm_contract_shares_state_gas_and_receipts() {
+        let mut blockchain = new_blockchain();
+        fund(&mut blockchain, "Alice", 100.0);
+        let receipt = submit(
+            &mut blockchain,
+            "Alice",
+            TransactionType::DeployContract {
+                code: evm_init(&EVM_COUNTER),
+                salt: None,
+                vm: Vm::Evm,
+            },
+            0.0,
+        );
+        let counter = receipt.contract_address.unwrap();
+        assert_eq!(blockchain.contracts[&counter].code, EVM_COUNTER);
+        assert_eq!(receipt.code_hash, Some(super::code_hash(&EVM_COUNTER)));
+
+        let count = |n: u8| {
+            let mut word = [0u8; 32];
+            word[31] = n;
+            word
+        };
+        let receipt = call(&mut blockchain, "Alice", &counter, "", &[], 5.0, 1.0);
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(receipt.return_data, count(1));
+        assert!(receipt.gas_used > 20_000);
+        assert_eq!(receipt.events[0].contract_address, counter);
+        assert_eq!(receipt.events[0].topics, vec![count(1)]);
+        assert_eq!(
+            blockchain.contract_storage[&counter][&"0".repeat(64)],
+            count(1)
+        );
+        let deposit = 96.0 * static_config::STORAGE_DEPOSIT_PER_BYTE;
+        assert_eq!(
+            blockchain.wallets.wallets[&counter].storage_deposit,
+            deposit
+        );
+        assert_eq!(blockchain.wallets.wallets[&counter].balance, 5.0 - deposit);
+
+        // Wasm contracts call EVM contracts like any other contract
+        let proxy = deploy(&mut blockchain, "Alice", PROXY);
+        fund(&mut blockchain, &proxy, 10.0);
+        let receipt = call(
+            &mut blockchain,
+            "Alice",
+            &proxy,
+            "forward",
+            counter.as_bytes(),
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(receipt.return_data, count(2));
+        // Queries are static calls, in which SSTORE halts
+        let query = blockchain.query_contract(&counter, "", &[]).unwrap();
+        assert_eq!(query.exit_code, -1);
+        assert_eq!(
+            blockchain.contract_storage[&counter][&"0".repeat(64)],
+            count(2)
+        );
+
+        let receipt = submit(
+            &mut blockchain,
+            "Alice",
+            TransactionType::DeployContract {
+                code: evm_init(&EVM_REVERTER),
+                salt: None,
+                vm: Vm::Evm,
+            },
+            0.0,
+        );
+        let reverter = receipt.contract_address.unwrap();
+        let balance = blockchain.wallets.wallets["Alice"].balance;
+        let receipt = call(&mut blockchain, "Alice", &reverter, "", &[], 5.0, 1.0);
+        assert_eq!(receipt.exit_code, 1);
+        assert!(!blockchain.contract_storage.contains_key(&reverter));
+        assert_eq!(blockchain.wallets.wallets["Alice"].balance, balance - 1.0);
+
+        // Creation code that reverts leaves no trace of the deployment
+        let nonce = blockchain.wallets.wallets["Alice"].nonce;
+        let receipt = submit(
+            &mut blockchain,
+            "Alice",
+            TransactionType::DeployContract {
+                // REVERT with no data
+                code: vec![0x60, 0x00, 0x60, 0x00, 0xfd],
+                salt: None,
+                vm: Vm::Evm,
+            },
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 1);
+        let failed = address::derive("Alice", nonce);
+        assert_eq!(blockchain.wallets.wallets["Alice"].nonce, nonce);
+        assert!(!blockchain.wallets.wallets.contains_key(&failed));
+
+        // Precompiles are not available
+        let receipt = submit(
+            &mut blockchain,
+            "Alice",
+            TransactionType::DeployContract {
+                code: evm_init(&EVM_PRECOMPILE_CALLER),
+                salt: None,
+                vm: Vm::Evm,
+            },
+            0.0,
+        );
+        let precompile_caller = receipt.contract_address.unwrap();
+        let receipt = call(
+            &mut blockchain,
+            "Alice",
+            &precompile_caller,
+            "",
+            &[],
+            0.0,
+            0.0,
+        );
+        assert_eq!(receipt.exit_code, 0);
+        assert_eq!(receipt.return_data, count(0));
+
+        let upgrade = TransactionType::UpgradeContract {
+            contract_address: counter,
+            code: wat::parse_str(ECHO).unwrap(),
+            migrate: None,
+        };
+        assert!(blockchain
+            .add_block(vec![Transaction::new("Alice".to_string(), upgrade, 0.0)])
+            .is_err());
+    }
 }
@@ -0,0 +1,49 @@
+use crate::config::static_config;
+use crate::contracts::code_hash;
+use std::collections::{HashMap, VecDeque};
+use wasmi::{Config, Engine, Module};
+
+/// Compiled modules keyed by code hash, so that a contract is compiled once
+/// instead of on every call. The oldest module is evicted when full.
+#[derive(Debug)]
+pub struct ModuleCache {
+    engine: Engine,
+    modules: HashMap<String, Module>,
+    order: VecDeque<String>,
+}
+
+impl Default for ModuleCache {
+    fn default() -> Self {
+        let mut config = Config::default();
+        config.consume_fuel(true);
+        Self {
+            engine: Engine::new(&config),
+            modules: HashMap::new(),
+            order: VecDeque::new(),
+        }
+    }
+}
+
+impl ModuleCache {
+    /// Engine the cached modules are compiled with
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn get_or_compile(&mut self, code: &[u8]) -> Result<Module, String> {
+        let hash = code_hash(code);
+        if let Some(module) = self.modules.get(&hash) {
+            return Ok(module.clone());
+        }
+        let module = Module::new(&self.engine, code)
+            .map_err(|e| format!("Failed to load Wasm module: {:?}", e))?;
+        if self.order.len() == static_config::MODULE_CACHE_SIZE {
+            if let Some(oldest) = self.order.pop_front() {
+                self.modules.remove(&oldest);
+            }
+        }
+        self.order.push_back(hash.clone());
+        self.modules.insert(hash, module.clone());
+        Ok(module)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::config::static_config;
+use wasmparser_nostd::{
+    ExternalKind, FunctionBody, Operator, Parser, Payload, TypeRef, ValidPayload, Validator,
+    WasmFeatures,
+};
+
+/// Proposals whose behaviour may differ between engines are turned off,
+/// SIMD is allowed only when enabled in the static config.
+fn features() -> WasmFeatures {
+    WasmFeatures {
+        simd: static_config::CONTRACT_WASM_SIMD,
+        relaxed_simd: false,
+        threads: false,
+        multi_memory: false,
+        exceptions: false,
+        memory64: false,
+        component_model: false,
+        memory_control: false,
+        ..WasmFeatures::default()
+    }
+}
+
+fn invalid(reason: impl std::fmt::Display) -> String {
+    format!("Invalid Wasm module: {}", reason)
+}
+
+/// Checks at deploy time that `code` is a module every node executes the same
+/// way: a valid module within the size and memory limits that imports only
+/// host functions, exports its memory and at least one method, and uses no
+/// floats besides the f64 amounts of the host ABI.
+pub fn validate(code: &[u8]) -> Result<(), String> {
+    if code.len() > static_config::MAX_CONTRACT_CODE_SIZE {
+        return Err(invalid(format!(
+            "code size {} exceeds {} bytes",
+            code.len(),
+            static_config::MAX_CONTRACT_CODE_SIZE
+        )));
+    }
+
+    let mut validator = Validator::new_with_features(features());
+    let mut exports_memory = false;
+    let mut exports_method = false;
+    for payload in Parser::new(0).parse_all(code) {
+        let payload = payload.map_err(invalid)?;
+        if let ValidPayload::Func(func, body) = validator.payload(&payload).map_err(invalid)? {
+            func.into_validator(Default::default())
+                .validate(&body)
+                .map_err(invalid)?;
+            check_no_f32(&body)?;
+        }
+
+        match payload {
+            Payload::ImportSection(reader) => {
+                for import in reader {
+                    let import = import.map_err(invalid)?;
+                    if import.module != "env" || !matches!(import.ty, TypeRef::Func(_)) {
+                        return Err(invalid(format!(
+                            "import {}.{} is not a host function",
+                            import.module, import.name
+                        )));
+                    }
+                }
+            }
+            Payload::MemorySection(reader) => {
+                for memory in reader {
+                    let memory = memory.map_err(invalid)?;
+                    let pages = memory.maximum.unwrap_or(memory.initial);
+                    if memory.initial > static_config::MAX_CONTRACT_MEMORY_PAGES
+                        || pages > static_config::MAX_CONTRACT_MEMORY_PAGES
+                    {
+                        return Err(invalid(format!(
+                            "memory exceeds {} pages",
+                            static_config::MAX_CONTRACT_MEMORY_PAGES
+                        )));
+                    }
+                }
+            }
+            Payload::ExportSection(reader) => {
+                for export in reader {
+                    let export = export.map_err(invalid)?;
+                    match export.kind {
+                        ExternalKind::Memory if export.name == "memory" => exports_memory = true,
+                        ExternalKind::Func => exports_method = true,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if !exports_memory {
+        return Err(invalid("missing memory export"));
+    }
+    if !exports_method {
+        return Err(invalid("no exported methods"));
+    }
+    Ok(())
+}
+
+/// Rejects f32 instructions. No host function takes or returns an f32, so
+/// without these instructions no f32 value can be produced.
+fn check_no_f32(body: &FunctionBody) -> Result<(), String> {
+    for operator in body.get_operators_reader().map_err(invalid)? {
+        let operator = operator.map_err(invalid)?;
+        let uses_f32 = matches!(
+            operator,
+            Operator::F32Load { .. }
+                | Operator::F32Store { .. }
+                | Operator::F32Const { .. }
+                | Operator::F32Eq
+                | Operator::F32Ne
+                | Operator::F32Lt
+                | Operator::F32Gt
+                | Operator::F32Le
+                | Operator::F32Ge
+                | Operator::F32Abs
+                | Operator::F32Neg
+                | Operator::F32Ceil
+                | Operator::F32Floor
+                | Operator::F32Trunc
+                | Operator::F32Nearest
+                | Operator::F32Sqrt
+                | Operator::F32Add
+                | Operator::F32Sub
+                | Operator::F32Mul
+                | Operator::F32Div
+                | Operator::F32Min
+                | Operator::F32Max
+                | Operator::F32Copysign
+                | Operator::I32TruncF32S
+                | Operator::I32TruncF32U
+                | Operator::I64TruncF32S
+                | Operator::I64TruncF32U
+                | Operator::F32ConvertI32S
+                | Operator::F32ConvertI32U
+                | Operator::F32ConvertI64S
+                | Operator::F32ConvertI64U
+                | Operator::F32DemoteF64
+                | Operator::F64PromoteF32
+                | Operator::I32ReinterpretF32
+                | Operator::F32ReinterpretI32
+                | Operator::I32TruncSatF32S
+                | Operator::I32TruncSatF32U
+                | Operator::I64TruncSatF32S
+                | Operator::I64TruncSatF32U
+        );
+        if uses_f32 {
+            return Err(invalid(format!("f32 instruction {:?}", operator)));
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,9 @@
 pub mod bchain_error;
+pub mod clock;
 pub mod message;
 pub mod primitives;
+pub mod snapshot;
+pub mod state_history;
 
 mod chain;
 mod config;
@@ -11,11 +14,16 @@ mod wallets;
 
 use bchain_error::BChainError;
 use chain::Chain;
+use clock::{Clock, SystemClock};
 use config::{config_utils, static_config};
+use contracts::{BlockContext, Checkpoint, ExecutionContext, Journal, ModuleCache};
 use message::BlockchainFacade;
-use patricia_merkle_trie::state_root;
+use patricia_merkle_trie::{merkle_tree, state_root};
 use primitives::*;
+use snapshot::Snapshot;
+use state_history::{StateHistory, StateRetention};
 use std::collections::HashMap;
+use std::sync::Arc;
 use validators::TwoEpochValidators;
 use wallets::Wallets;
 
@@ -25,9 +33,20 @@ pub use config::static_config::GENESIS;
 pub struct Blockchain {
     chain: Chain,
     wallets: Wallets,
-    contracts: HashMap<String, Vec<u8>>,
+    contracts: HashMap<String, Contract>,
     contract_storage: HashMap<String, HashMap<String, Vec<u8>>>,
+    /// Previous storage values and contracts, for checkpoints to restore
+    storage_journal: Journal<(String, String), Vec<u8>>,
+    contracts_journal: Journal<String, Contract>,
     validators: TwoEpochValidators,
+    /// Block whose transactions are being executed, read by contracts
+    block_context: BlockContext,
+    modules: ModuleCache,
+    clock: Arc<dyn Clock>,
+    /// State at the last snapshot height, offered to syncing nodes
+    snapshot: Option<Snapshot>,
+    state_history: StateHistory,
+    retention: StateRetention,
 }
 
 impl BlockchainFacade for Blockchain {
@@ -52,22 +71,367 @@ impl BlockchainFacade for Blockchain {
             .get(user)
             .ok_or(BChainError::UserNotFound(user.to_string()))
     }
+
+    fn get_wallet_at(
+        &self,
+        user: &str,
+        height: usize,
+    ) -> Result<(Wallet, StateProof), BChainError> {
+        match self.prove_state(
+            height,
+            |entry| matches!(entry, StateEntry::Wallet { user: owner, .. } if owner == user),
+        )? {
+            Some((StateEntry::Wallet { wallet, .. }, proof)) => Ok((wallet, proof)),
+            _ => Err(BChainError::UserNotFound(user.to_string())),
+        }
+    }
+
+    fn get_contract_storage_at(
+        &self,
+        contract_address: &str,
+        key: &str,
+        height: usize,
+    ) -> Result<(Vec<u8>, StateProof), BChainError> {
+        match self.prove_state(height, |entry| {
+            matches!(
+                entry,
+                StateEntry::Storage { address, key: stored, .. }
+                    if address == contract_address && stored == key
+            )
+        })? {
+            Some((StateEntry::Storage { value, .. }, proof)) => Ok((value, proof)),
+            _ => Err(BChainError::StorageNotFound(
+                contract_address.to_string(),
+                key.to_string(),
+            )),
+        }
+    }
+
+    fn query_contract(
+        &mut self,
+        contract_address: &str,
+        method: &str,
+        input: &[u8],
+    ) -> Result<Receipt, BChainError> {
+        let contract = self
+            .contracts
+            .get(contract_address)
+            .ok_or(BChainError::ContractNotFound(contract_address.to_string()))?;
+        let (vm, code) = (contract.vm, contract.code.clone());
+        let context = ExecutionContext {
+            read_only: true,
+            ..ExecutionContext::new(contract_address, "", input)
+        };
+        // Queries see the next block, stamped like the current tip
+        let timestamp = self.chain.get_last_block().unwrap().timestamp;
+        let block_context = self
+            .next_block_context(timestamp)
+            .map_err(|e| BChainError::QueryFailure(contract_address.to_string(), e))?;
+        let previous_block_context = std::mem::replace(&mut self.block_context, block_context);
+        let checkpoint = Checkpoint::new(self);
+        let result = contracts::execute(self, vm, &code, method, context);
+        checkpoint.restore(self);
+        self.block_context = previous_block_context;
+        result.map_err(|e| BChainError::QueryFailure(contract_address.to_string(), e))
+    }
+
+    fn trace_transaction(&self, height: usize, index: usize) -> Result<Trace, BChainError> {
+        let block = self
+            .chain
+            .get_block_by_idx(height)
+            .filter(|_| height > 0)
+            .ok_or(BChainError::BlockNotFound(height))?;
+        let tx = block
+            .transactions
+            .get(index)
+            .ok_or(BChainError::TransactionNotFound(height, index))?;
+        if self.chain.first_height() > 0 {
+            return Err(BChainError::ReplayFailure(
+                height,
+                "blocks before the snapshot the chain started from are missing".to_string(),
+            ));
+        }
+
+        // Rebuild the state the block started from by replaying the chain
+        let mut replica = Blockchain {
+            chain: self.chain.truncated(1),
+            clock: self.clock.clone(),
+            ..Blockchain::new()
+        };
+        for replayed in 1..height {
+            let replayed_block = self.chain.get_block_by_idx(replayed).unwrap();
+            replica
+                .add_block_at(
+                    replayed_block.transactions.clone(),
+                    replayed_block.timestamp,
+                    Some(replayed_block),
+                )
+                .map_err(|e| BChainError::ReplayFailure(replayed, e))?;
+        }
+
+        replica.block_context = replica
+            .next_block_context(block.timestamp)
+            .map_err(|e| BChainError::ReplayFailure(height, e))?;
+        for previous in &block.transactions[..index] {
+            replica
+                .apply_transaction(previous, None)
+                .map_err(|e| BChainError::ReplayFailure(height, e))?;
+        }
+        let mut trace = Trace::default();
+        replica
+            .apply_transaction(tx, Some(&mut trace))
+            .map_err(|e| BChainError::ReplayFailure(height, e))?;
+        Ok(trace)
+    }
+
+    fn get_inclusion_proof(
+        &self,
+        height: usize,
+        tx_hash: &Hash,
+    ) -> Result<InclusionProof, BChainError> {
+        self.chain
+            .get_block_by_idx(height)
+            .ok_or(BChainError::BlockNotFound(height))?
+            .inclusion_proof(tx_hash)
+            .ok_or(BChainError::TransactionNotIncluded(height, *tx_hash))
+    }
+
+    fn get_transaction_locations(&self, tx_hash: &Hash) -> &[TxLocation] {
+        self.chain.get_tx_locations(tx_hash)
+    }
+
+    fn get_account_history(&self, account: &str, offset: usize, limit: usize) -> &[TxLocation] {
+        let history = self.chain.get_account_history(account);
+        let start = offset.min(history.len());
+        &history[start..history.len().min(start.saturating_add(limit))]
+    }
+
+    fn get_snapshot_manifest(&self) -> Result<SnapshotManifest, BChainError> {
+        let snapshot = self
+            .snapshot
+            .as_ref()
+            .ok_or(BChainError::SnapshotNotAvailable)?;
+        let first = snapshot.height() - snapshot::recent_blocks_wanted(snapshot.height());
+        let recent_blocks = (first..snapshot.height())
+            .map(|height| self.chain.get_block_by_idx(height).cloned())
+            .collect::<Option<_>>()
+            .ok_or(BChainError::SnapshotNotAvailable)?;
+        Ok(snapshot.manifest(recent_blocks))
+    }
+
+    fn get_snapshot_chunk(
+        &self,
+        height: usize,
+        index: usize,
+    ) -> Result<SnapshotChunk, BChainError> {
+        self.snapshot
+            .as_ref()
+            .filter(|snapshot| snapshot.height() == height)
+            .ok_or(BChainError::SnapshotNotAvailable)?
+            .chunk(index)
+            .ok_or(BChainError::InvalidSnapshot(format!(
+                "no chunk {} in snapshot at height {}",
+                index, height
+            )))
+    }
+
+    fn get_storage(&self, contract_address: &str, key: &str) -> Option<&Vec<u8>> {
+        self.contract_storage
+            .get(contract_address)
+            .and_then(|storage| storage.get(key))
+    }
+
+    fn get_height(&self) -> usize {
+        self.chain.len() - 1
+    }
+
+    fn get_receipts(&self, block_height: usize) -> Result<&Vec<Receipt>, BChainError> {
+        self.chain
+            .get_receipts_by_idx(block_height)
+            .ok_or(BChainError::BlockNotFound(block_height))
+    }
+
+    fn get_events(
+        &self,
+        contract_address: &str,
+        topic: Option<&[u8; 32]>,
+        from_height: usize,
+        to_height: usize,
+    ) -> Vec<(usize, Event)> {
+        let mut events = Vec::new();
+        let from_height = from_height.max(self.chain.first_height());
+        for height in from_height..=to_height.min(self.chain.len().saturating_sub(1)) {
+            let bloom = &self.chain.get_block_by_idx(height).unwrap().logs_bloom;
+            if !bloom.contains(contract_address.as_bytes())
+                || topic.is_some_and(|topic| !bloom.contains(topic))
+            {
+                continue;
+            }
+            let receipts = self.chain.get_receipts_by_idx(height).unwrap();
+            events.extend(
+                receipts
+                    .iter()
+                    .flat_map(|receipt| &receipt.events)
+                    .filter(|event| event.contract_address == contract_address)
+                    .filter(|event| topic.is_none_or(|topic| event.topics.contains(topic)))
+                    .map(|event| (height, event.clone())),
+            );
+        }
+        events
+    }
 }
 
 impl Blockchain {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Blockchain that stamps blocks and checks their timestamps with `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         let mut wallets = Wallets::default();
-        wallets
-            .wallets
-            .insert(static_config::GENESIS.to_string(), Wallet::new(1000.));
-        let (state_root, _) = state_root::compute(&wallets);
+        wallets.insert(static_config::GENESIS.to_string(), Wallet::new(1000.));
+        let validators = TwoEpochValidators::new(static_config::EPOCH_HEIGHT);
+        let entries = state_root::entries(&wallets, &HashMap::new(), &HashMap::new(), &validators);
+        let (state_root, _) =
+            state_root::compute(&entries).expect("genesis state has a canonical encoding");
         Blockchain {
             chain: Chain::new(state_root),
             wallets,
             contracts: HashMap::new(),
             contract_storage: HashMap::new(),
+            storage_journal: Journal::default(),
+            contracts_journal: Journal::default(),
+            validators,
+            block_context: BlockContext::default(),
+            modules: ModuleCache::default(),
+            clock,
+            snapshot: None,
+            state_history: StateHistory::new(0, &entries)
+                .expect("genesis state has a canonical encoding"),
+            retention: StateRetention::Pruned {
+                recent_blocks: static_config::STATE_RETENTION_BLOCKS,
+            },
+        }
+    }
+
+    /// Blockchain continuing from the last of `blocks`, the block at
+    /// `height`, with the state of that block rebuilt from `entries`. Only
+    /// `blocks` are available from the chain before it.
+    fn from_snapshot(
+        height: usize,
+        blocks: Vec<Block>,
+        entries: Vec<StateEntry>,
+        clock: Arc<dyn Clock>,
+    ) -> Self {
+        let mut blockchain = Blockchain {
+            chain: Chain::from_snapshot(height, blocks),
+            wallets: Wallets::default(),
+            contracts: HashMap::new(),
+            contract_storage: HashMap::new(),
+            storage_journal: Journal::default(),
+            contracts_journal: Journal::default(),
             validators: TwoEpochValidators::new(static_config::EPOCH_HEIGHT),
+            block_context: BlockContext::default(),
+            modules: ModuleCache::default(),
+            clock,
+            snapshot: None,
+            // The entries added up to the state root, so they all hashed
+            state_history: StateHistory::new(height, &entries)
+                .expect("snapshot entries have a canonical encoding"),
+            retention: StateRetention::Pruned {
+                recent_blocks: static_config::STATE_RETENTION_BLOCKS,
+            },
+        };
+        for entry in entries {
+            match entry {
+                StateEntry::Wallet { user, wallet } => {
+                    blockchain.wallets.insert(user, wallet);
+                }
+                StateEntry::Contract { address, contract } => {
+                    blockchain.contracts.insert(address, contract);
+                }
+                StateEntry::Storage {
+                    address,
+                    key,
+                    value,
+                } => {
+                    blockchain
+                        .contract_storage
+                        .entry(address)
+                        .or_default()
+                        .insert(key, value);
+                }
+                StateEntry::Validators { current, next } => {
+                    blockchain.validators = TwoEpochValidators::from_lists(current, next);
+                }
+            }
+        }
+        blockchain
+    }
+
+    /// Leaves of the state tree of the current state
+    fn state_entries(&self) -> Vec<StateEntry> {
+        state_root::entries(
+            &self.wallets,
+            &self.contracts,
+            &self.contract_storage,
+            &self.validators,
+        )
+    }
+
+    /// Chooses which past states to keep, dropping those no longer wanted
+    pub fn set_state_retention(&mut self, retention: StateRetention) {
+        self.retention = retention;
+        self.prune_state_history();
+    }
+
+    fn prune_state_history(&mut self) {
+        if let StateRetention::Pruned { recent_blocks } = self.retention {
+            let keep_from = self.chain.len().saturating_sub(recent_blocks.max(1));
+            let checkpoint = self.snapshot.as_ref().map(Snapshot::height);
+            self.state_history.prune(keep_from, checkpoint);
+        }
+    }
+
+    /// Leaves of the state tree after the block at `height`
+    fn state_at(&self, height: usize) -> Result<Vec<StateEntry>, BChainError> {
+        if height >= self.chain.len() {
+            return Err(BChainError::BlockNotFound(height));
+        }
+        if !self.state_history.is_available(height) {
+            return Err(BChainError::StatePruned(height));
         }
+        Ok(self.state_history.state_at(height))
+    }
+
+    /// First entry of the state after the block at `height` matching
+    /// `wanted`, with its proof against the block's state root
+    fn prove_state(
+        &self,
+        height: usize,
+        wanted: impl Fn(&StateEntry) -> bool,
+    ) -> Result<Option<(StateEntry, StateProof)>, BChainError> {
+        let mut entries = self.state_at(height)?;
+        let Some(leaf) = entries.iter().position(wanted) else {
+            return Ok(None);
+        };
+        let (_, tree) =
+            state_root::compute(&entries).expect("recorded states were hashed when recorded");
+        let proof = StateProof {
+            height,
+            siblings: merkle_tree::proof(&tree, leaf),
+        };
+        Ok(Some((entries.swap_remove(leaf), proof)))
+    }
+
+    /// Recomputes the transaction and account history indexes from the blocks
+    pub fn rebuild_indexes(&mut self) {
+        self.chain.rebuild_index();
+    }
+
+    /// Slot the clock is currently in, None before genesis
+    pub fn current_slot(&self) -> Option<u64> {
+        config_utils::get_slot(self.clock.now())
     }
 
     fn get_epoch_seed(&self, epoch: usize) -> String {
@@ -84,14 +448,14 @@ impl Blockchain {
                     .get_block_by_idx(validators_consensus_block)
                     .unwrap()
                     .hash
-                    .clone()
+                    .to_string()
             }
         }
     }
 
     fn distribute_rewards(&mut self) {
         for user in self.validators.get_current_epoch_validators() {
-            let wallet = self.wallets.wallets.get_mut(user).unwrap();
+            let wallet = self.wallets.get_mut(user).unwrap();
             let user_reward = wallet.staked * static_config::REWARD_RATE_PER_EPOCH;
             wallet.balance += user_reward;
         }
@@ -113,9 +477,28 @@ impl Blockchain {
         self.wallets.return_stakes(epoch);
     }
 
+    /// Imports a block from a peer. A rejected block leaves the state as it
+    /// was, though its transactions may have run before it was rejected.
     fn process_block(&mut self, block: Block) -> Result<(), String> {
+        let validators = self.validators.clone();
+        let checkpoint = Checkpoint::new(self);
+        if let Err(e) = self.import_block(&block) {
+            checkpoint.restore(self);
+            self.validators = validators;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn import_block(&mut self, block: &Block) -> Result<(), String> {
         self.on_first_block_of_epoch();
-        if block.hash != block.calculate_hash() {
+        // Blocks from peers may hold NaN amounts, which cannot be hashed
+        let (Ok(hash), Ok(transactions_root)) =
+            (block.calculate_hash(), block.calculate_transactions_root())
+        else {
+            return Err("Block has no canonical encoding".to_string());
+        };
+        if block.hash != hash || block.transactions_root != transactions_root {
             return Err("Block hash corrupted".to_string());
         }
         if let Some(prev_block) = self.chain.get_last_block() {
@@ -126,82 +509,468 @@ impl Blockchain {
                 ));
             }
         }
-        self.add_block(block.transactions)
+        self.add_block_at(block.transactions.clone(), block.timestamp, Some(block))
     }
 
     fn add_block(&mut self, transactions: Vec<Transaction>) -> Result<(), String> {
-        let block_height = self.chain.len();
-        let slot_in_epoch = block_height % static_config::EPOCH_HEIGHT;
+        let timestamp = self.clock.now();
+        self.add_block_at(transactions, timestamp, None)
+    }
+
+    /// Median timestamp of the last `MEDIAN_TIME_BLOCKS` blocks
+    fn median_time_past(&self) -> i64 {
+        let len = self.chain.len();
+        let mut timestamps: Vec<i64> = (len.saturating_sub(static_config::MEDIAN_TIME_BLOCKS)..len)
+            .filter_map(|idx| self.chain.get_block_by_idx(idx))
+            .map(|block| block.timestamp)
+            .collect();
+        timestamps.sort_unstable();
+        timestamps[timestamps.len() / 2]
+    }
+
+    /// Consensus rules on the timestamp of the block following the tip. The
+    /// block's slot is the one whose window holds its timestamp, and each
+    /// slot holds at most one block.
+    fn check_timestamp(&self, timestamp: i64) -> Result<(), String> {
+        let median_time_past = self.median_time_past();
+        if timestamp <= median_time_past {
+            return Err(format!(
+                "Block timestamp {} is not after the median {} of recent blocks",
+                timestamp, median_time_past
+            ));
+        }
+        let slot = config_utils::get_slot(timestamp)
+            .ok_or_else(|| format!("Block timestamp {} is before genesis", timestamp))?;
+        let parent_slot = self
+            .chain
+            .get_last_block()
+            .and_then(|block| config_utils::get_slot(block.timestamp))
+            .unwrap_or_default();
+        if slot <= parent_slot {
+            return Err(format!(
+                "Block timestamp {} falls in slot {}, not after the slot {} of its parent",
+                timestamp, slot, parent_slot
+            ));
+        }
+        let now = self.clock.now();
+        if timestamp > now + static_config::MAX_FUTURE_BLOCK_TIME {
+            return Err(format!(
+                "Block timestamp {} is too far ahead of local time {}",
+                timestamp, now
+            ));
+        }
+        Ok(())
+    }
+
+    /// Context of the block following the current tip, validated by the
+    /// validator of the slot `timestamp` falls in
+    fn next_block_context(&self, timestamp: i64) -> Result<BlockContext, String> {
+        let height = self.chain.len();
+        let slot = config_utils::get_slot(timestamp)
+            .ok_or_else(|| format!("Block timestamp {} is before genesis", timestamp))?;
+        let slot_in_epoch = slot as usize % static_config::EPOCH_HEIGHT;
         let validator = self
             .validators
             .get_current_epoch_validators()
             .get(slot_in_epoch)
             .ok_or("No validators available")?
             .clone();
+        Ok(BlockContext {
+            height,
+            timestamp,
+            epoch: config_utils::get_epoch(height),
+            validator,
+        })
+    }
+
+    /// Executes `transactions` in a block stamped with `timestamp`, which is
+    /// fixed up front so contracts see the same block context when the block
+    /// is produced and when it is imported. An `imported` block is added only
+    /// if it is the block the transactions rebuild.
+    fn add_block_at(
+        &mut self,
+        transactions: Vec<Transaction>,
+        timestamp: i64,
+        imported: Option<&Block>,
+    ) -> Result<(), String> {
+        self.check_timestamp(timestamp)?;
+        self.block_context = self.next_block_context(timestamp)?;
+        let validator = self.block_context.validator.clone();
         let previous_block = self.chain.get_last_block().unwrap().clone();
+        let mut receipts = Vec::with_capacity(transactions.len());
 
         for tx in &transactions {
-            match &tx.tx_type {
-                TransactionType::Stake { user, amount } => {
-                    self.wallets.stake(user, *amount, tx.fee)?;
-                }
-                TransactionType::Unstake { user, amount } => {
-                    self.wallets.unstake(user, block_height, *amount, tx.fee)?;
-                }
-                TransactionType::Transfer {
-                    sender,
-                    receiver,
-                    amount,
-                } => {
-                    self.wallets.transfer(sender, receiver, *amount, tx.fee)?;
-                }
-                TransactionType::DeployContract { code } => {
-                    let contract_address = format!("contract_{}", self.contracts.len());
-                    self.contracts
-                        .insert(contract_address.clone(), code.clone());
-                    println!("Deployed contract at address: {}", contract_address);
-                }
-                TransactionType::CallContract { contract_address } => {
-                    // Deduct the fee from the sender (Alice)
-                    let sender_wallet = self
-                        .wallets
-                        .wallets
-                        .get_mut(&tx.sender)
-                        .ok_or("Sender not found")?;
-                    if sender_wallet.balance < tx.fee {
-                        return Err("Insufficient balance for fee".to_string());
-                    }
-                    sender_wallet.balance -= tx.fee;
-                    let x = &self
-                        .contracts
-                        .get(contract_address)
-                        .ok_or("Contract not found")?
-                        .clone();
-                    contracts::execute(self, x, contract_address, &tx.sender)?;
-                }
+            receipts.push(self.apply_transaction(tx, None)?);
+        }
+
+        let mut logs_bloom = Bloom::default();
+        for event in receipts.iter().flat_map(|receipt| &receipt.events) {
+            logs_bloom.accrue(event.contract_address.as_bytes());
+            for topic in &event.topics {
+                logs_bloom.accrue(topic);
             }
         }
 
-        let (state_root, _) = state_root::compute(&self.wallets);
+        // The state root covers the fees paid to the validator
+        let total_fees: f64 = transactions.iter().map(|tx| tx.fee).sum();
+        let validator_wallet = self.wallets.get_mut(&validator).unwrap();
+        validator_wallet.balance += total_fees;
+        let entries = self.state_entries();
+        let (state_root, tree) = state_root::compute(&entries)?;
         let new_block = Block::new(
+            timestamp,
             transactions.clone(),
-            previous_block.hash.clone(),
+            previous_block.hash,
             validator.clone(),
             state_root,
+            logs_bloom,
+        )?;
+        if let Some(imported) = imported {
+            if imported.state_root != new_block.state_root {
+                return Err(format!(
+                    "Block's state root {} does not match the local state root {}",
+                    imported.state_root, new_block.state_root
+                ));
+            }
+            if imported.hash != new_block.hash {
+                return Err(
+                    "Block does not match the block rebuilt from its transactions".to_string(),
+                );
+            }
+        }
+
+        let height = self.chain.len();
+        self.state_history.record(height, &entries)?;
+        if height.is_multiple_of(static_config::SNAPSHOT_INTERVAL) {
+            self.snapshot = Some(Snapshot::new(height, new_block.clone(), entries, tree));
+        }
+        self.chain.push(new_block, receipts);
+        self.prune_state_history();
+        Ok(())
+    }
+
+    /// Executes a transaction of the block in `block_context`. Contract calls
+    /// are recorded into `trace` when given.
+    fn apply_transaction(
+        &mut self,
+        tx: &Transaction,
+        trace: Option<&mut Trace>,
+    ) -> Result<Receipt, String> {
+        // Amounts must have a canonical encoding to be hashed into the block
+        primitives::encode(tx)?;
+        let mut receipt = Receipt::default();
+        match &tx.tx_type {
+            TransactionType::Stake { user, amount } => {
+                self.wallets.stake(user, *amount, tx.fee)?;
+            }
+            TransactionType::Unstake { user, amount } => {
+                self.wallets
+                    .unstake(user, self.block_context.height, *amount, tx.fee)?;
+            }
+            TransactionType::Transfer {
+                sender,
+                receiver,
+                amount,
+            } => {
+                self.wallets.transfer(sender, receiver, *amount, tx.fee)?;
+            }
+            TransactionType::DeployContract { code, salt, vm } => {
+                receipt = self.deploy_contract(tx, code, salt.as_deref(), *vm)?;
+            }
+            TransactionType::CallContract {
+                contract_address,
+                value,
+                method,
+                input,
+            } => {
+                receipt = self.call_contract(tx, contract_address, *value, method, input, trace)?;
+            }
+            TransactionType::UpgradeContract {
+                contract_address,
+                code,
+                migrate,
+            } => {
+                receipt = self.upgrade_contract(tx, contract_address, code, migrate.as_deref())?;
+            }
+            TransactionType::DestroyContract { contract_address } => {
+                self.destroy_contract(tx, contract_address)?;
+            }
+            TransactionType::Batch { operations, mode } => {
+                receipt = self.apply_batch(tx, operations, *mode)?;
+            }
+        }
+        Ok(receipt)
+    }
+
+    /// Runs each operation as a fee-less transaction of the batch's sender.
+    /// A failed operation is reverted, and so is the rest of the batch in
+    /// all-or-nothing mode, where the receipt takes the failure's exit code.
+    fn apply_batch(
+        &mut self,
+        tx: &Transaction,
+        operations: &[TransactionType],
+        mode: BatchMode,
+    ) -> Result<Receipt, String> {
+        if operations.is_empty() {
+            return Err("Empty batch".to_string());
+        }
+        for operation in operations {
+            let account = match operation {
+                TransactionType::Batch { .. } => {
+                    return Err("Batches cannot be nested".to_string());
+                }
+                TransactionType::Transfer { sender, .. } => sender,
+                TransactionType::Stake { user, .. } | TransactionType::Unstake { user, .. } => user,
+                _ => &tx.sender,
+            };
+            if *account != tx.sender {
+                return Err(format!("Batch of {} cannot act for {}", tx.sender, account));
+            }
+        }
+        self.wallets.charge_fee(&tx.sender, tx.fee)?;
+
+        let batch_checkpoint = Checkpoint::new(self);
+        let mut receipt = Receipt::default();
+        for (index, operation) in operations.iter().enumerate() {
+            let checkpoint = (mode == BatchMode::BestEffort).then(|| Checkpoint::new(self));
+            let operation_tx = Transaction::new(tx.sender.clone(), operation.clone(), 0.0);
+            let result = self
+                .apply_transaction(&operation_tx, None)
+                .unwrap_or_else(|e| Receipt {
+                    exit_code: -1,
+                    error: Some(e),
+                    ..Default::default()
+                });
+            receipt.gas_used += result.gas_used;
+            if result.exit_code == 0 {
+                receipt.events.extend(result.events.iter().cloned());
+            } else if let Some(checkpoint) = checkpoint {
+                checkpoint.restore(self);
+            } else {
+                batch_checkpoint.restore(self);
+                receipt.exit_code = result.exit_code;
+                receipt.error = Some(format!(
+                    "Operation {} failed: {}",
+                    index,
+                    result.error.as_deref().unwrap_or("non-zero exit code")
+                ));
+                receipt.events.clear();
+                receipt.operations.push(result);
+                return Ok(receipt);
+            }
+            receipt.operations.push(result);
+        }
+        Ok(receipt)
+    }
+
+    fn deploy_contract(
+        &mut self,
+        tx: &Transaction,
+        code: &[u8],
+        salt: Option<&[u8]>,
+        vm: Vm,
+    ) -> Result<Receipt, String> {
+        let deployer = tx.sender.as_str();
+        self.wallets.charge_fee(deployer, tx.fee)?;
+        match vm {
+            Vm::Wasm => {
+                contracts::validate(code)?;
+                self.modules.get_or_compile(code)?;
+            }
+            Vm::Evm if code.len() > static_config::MAX_CONTRACT_CODE_SIZE => {
+                return Err(format!("Contract code is {} bytes long", code.len()));
+            }
+            Vm::Evm => {}
+        }
+        let nonce = self
+            .wallets
+            .wallets
+            .get(deployer)
+            .ok_or("Deployer not found")?
+            .nonce;
+        let contract_address = match salt {
+            Some(salt) => contracts::address::derive_salted(deployer, salt, code),
+            None => contracts::address::derive(deployer, nonce),
+        };
+        if self.contracts.contains_key(&contract_address) {
+            return Err(format!(
+                "Contract already deployed at address: {}",
+                contract_address
+            ));
+        }
+        // A failed EVM deployment leaves neither the nonce nor the account behind
+        let checkpoint = Checkpoint::new(self);
+        self.wallets.get_or_create(deployer).nonce += 1;
+        self.wallets.get_or_create(&contract_address);
+
+        // EVM code is creation code, what it returns is the contract's code
+        let mut receipt = Receipt::default();
+        let code = match vm {
+            Vm::Wasm => code.to_vec(),
+            Vm::Evm => {
+                let context = ExecutionContext::new(&contract_address, deployer, &[]);
+                match contracts::execute(self, vm, code, "", context) {
+                    Ok(result) if result.exit_code == 0 => receipt = result,
+                    result => {
+                        checkpoint.restore(self);
+                        return result;
+                    }
+                }
+                std::mem::take(&mut receipt.return_data)
+            }
+        };
+        receipt.code_hash = Some(contracts::code_hash(&code));
+        self.write_contract(
+            &contract_address,
+            Some(Contract {
+                code,
+                admin: deployer.to_string(),
+                vm,
+            }),
         );
-        let validator_wallet = self.wallets.wallets.get_mut(&validator).unwrap();
-        validator_wallet.balance += new_block.total_fees;
+        println!("Deployed contract at address: {}", contract_address);
+        receipt.contract_address = Some(contract_address);
+        Ok(receipt)
+    }
+
+    fn call_contract(
+        &mut self,
+        tx: &Transaction,
+        contract_address: &str,
+        value: f64,
+        method: &str,
+        input: &[u8],
+        trace: Option<&mut Trace>,
+    ) -> Result<Receipt, String> {
+        if value < 0.0 {
+            return Err("Negative call value".to_string());
+        }
+        let contract = self
+            .contracts
+            .get(contract_address)
+            .ok_or("Contract not found")?;
+        let (vm, code) = (contract.vm, contract.code.clone());
+        // The fee is kept even if the call fails, everything else is
+        // committed only when the contract exits with 0
+        self.wallets.charge_fee(&tx.sender, tx.fee)?;
+        let checkpoint = Checkpoint::new(self);
+        let context = ExecutionContext {
+            value,
+            ..ExecutionContext::new(contract_address, &tx.sender, input)
+        };
+        let receipt = self
+            .wallets
+            .transfer(&tx.sender, contract_address, value, 0.0)
+            .and_then(|_| match trace {
+                Some(trace) => contracts::execute_traced(self, vm, &code, method, context).map(
+                    |(receipt, steps)| {
+                        *trace = steps;
+                        receipt
+                    },
+                ),
+                None => contracts::execute(self, vm, &code, method, context),
+            })
+            .unwrap_or_else(|e| Receipt {
+                exit_code: -1,
+                error: Some(e),
+                ..Default::default()
+            });
+        if receipt.exit_code != 0 {
+            checkpoint.restore(self);
+        }
+        Ok(receipt)
+    }
+
+    fn upgrade_contract(
+        &mut self,
+        tx: &Transaction,
+        contract_address: &str,
+        code: &[u8],
+        migrate: Option<&str>,
+    ) -> Result<Receipt, String> {
+        let contract = self
+            .contracts
+            .get(contract_address)
+            .ok_or("Contract not found")?;
+        if contract.admin != tx.sender {
+            return Err(format!(
+                "{} is not allowed to upgrade contract {}",
+                tx.sender, contract_address
+            ));
+        }
+        if contract.vm != Vm::Wasm {
+            return Err(format!(
+                "Contract {} runs on the EVM and cannot be upgraded",
+                contract_address
+            ));
+        }
+        contracts::validate(code)?;
+        self.modules.get_or_compile(code)?;
+        let previous = contract.clone();
+        self.wallets.charge_fee(&tx.sender, tx.fee)?;
+        let upgraded = Contract {
+            code: code.to_vec(),
+            ..previous.clone()
+        };
+        self.write_contract(contract_address, Some(upgraded));
+
+        let mut receipt = match migrate {
+            Some(method) => {
+                let checkpoint = Checkpoint::new(self);
+                let context = ExecutionContext::new(contract_address, &tx.sender, &[]);
+                let receipt = contracts::execute(self, Vm::Wasm, code, method, context)
+                    .unwrap_or_else(|e| Receipt {
+                        exit_code: -1,
+                        error: Some(e),
+                        ..Default::default()
+                    });
+                if receipt.exit_code != 0 {
+                    // A failed migration leaves the previous code in place
+                    checkpoint.restore(self);
+                    self.write_contract(contract_address, Some(previous));
+                    return Ok(receipt);
+                }
+                receipt
+            }
+            None => Receipt::default(),
+        };
+        println!("Upgraded contract at address: {}", contract_address);
+        receipt.contract_address = Some(contract_address.to_string());
+        receipt.code_hash = Some(contracts::code_hash(code));
+        Ok(receipt)
+    }
 
-        self.chain.push(new_block);
+    fn destroy_contract(&mut self, tx: &Transaction, contract_address: &str) -> Result<(), String> {
+        let contract = self
+            .contracts
+            .get(contract_address)
+            .ok_or("Contract not found")?;
+        if contract.admin != tx.sender {
+            return Err(format!(
+                "{} is not allowed to destroy contract {}",
+                tx.sender, contract_address
+            ));
+        }
+        self.wallets.charge_fee(&tx.sender, tx.fee)?;
+        let admin = contract.admin.clone();
+        if let Some(wallet) = self.wallets.remove(contract_address) {
+            self.wallets.get_or_create(&admin).balance += wallet.balance + wallet.storage_deposit;
+        }
+        self.write_contract(contract_address, None);
+        self.clear_storage(contract_address);
+        println!("Destroyed contract at address: {}", contract_address);
         Ok(())
     }
 
     fn is_valid(&self) -> bool {
-        for i in 1..self.chain.len() {
+        for i in self.chain.first_height() + 1..self.chain.len() {
             let current = &self.chain.get_block_by_idx(i).unwrap();
             let previous = &self.chain.get_block_by_idx(i - 1).unwrap();
 
-            if current.hash != current.calculate_hash() || current.previous_hash != previous.hash {
+            if current.calculate_hash() != Ok(current.hash)
+                || current.calculate_transactions_root() != Ok(current.transactions_root)
+                || current.previous_hash != previous.hash
+            {
                 return false;
             }
         }
@@ -222,6 +991,11 @@ mod tests {
     const EXCEESIVE_AMOUNT: f64 = 150.0;
     const SUFFICIENT_AMOUNT: f64 = 60.0;
 
+    /// Blockchain whose clock reaches a new slot for every block produced
+    pub(crate) fn new_blockchain() -> Blockchain {
+        Blockchain::with_clock(Arc::new(clock::TickingClock::default()))
+    }
+
     fn initiate_account(blockchain: &mut Blockchain, user: String) {
         blockchain
             .add_block(vec![Transaction::new(
@@ -263,16 +1037,16 @@ mod tests {
 
     #[test]
     fn test_genesis_block() {
-        let blockchain = Blockchain::new();
+        let blockchain = new_blockchain();
         assert_eq!(blockchain.chain.len(), 1);
         let first_block = blockchain.chain.get_block_by_idx(0).unwrap();
-        assert_eq!(first_block.previous_hash, "0".to_owned());
+        assert_eq!(first_block.previous_hash, Hash::default());
         assert_eq!(first_block.validator, static_config::GENESIS.to_owned());
     }
 
     #[test]
     fn test_ok_when_put_valid_stake() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = new_blockchain();
         println!("Genesis block: {:?}", blockchain.chain.get_block_by_idx(0));
 
         let account_1 = "Allice".to_string();
@@ -282,7 +1056,7 @@ mod tests {
 
     #[test]
     fn test_error_when_put_too_high_stake() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = new_blockchain();
         println!(
             "Genesis block: {:?}",
             blockchain.chain.get_block_by_idx(0).unwrap()
@@ -295,7 +1069,7 @@ mod tests {
 
     #[test]
     fn test_error_when_too_high_stake_put_after_tx() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = new_blockchain();
         println!("Genesis block: {:?}", blockchain.chain.get_block_by_idx(0));
 
         let account_1 = "Allice".to_string();
@@ -315,7 +1089,7 @@ mod tests {
 
     #[test]
     fn test_error_when_too_high_tx_after_stake_put() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = new_blockchain();
         println!("Genesis block: {:?}", blockchain.chain.get_block_by_idx(0));
 
         let account_1 = "Allice".to_string();
@@ -329,7 +1103,7 @@ mod tests {
 
     #[test]
     fn test_ok_when_high_stake_put_after_receiving() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = new_blockchain();
         println!("Genesis block: {:?}", blockchain.chain.get_block_by_idx(0));
 
         let account_1 = "Allice".to_string();
@@ -356,4 +1130,446 @@ mod tests {
             static_config::EPOCH_HEIGHT - 1
         );
     }
+
+    #[test]
+    fn test_block_hash_commits_to_transactions() {
+        let mut blockchain = new_blockchain();
+        initiate_account(&mut blockchain, "Alice".to_string());
+        let block = blockchain.chain.get_last_block().unwrap().clone();
+        assert_eq!(block.calculate_hash(), Ok(block.hash));
+        assert_eq!(
+            block.calculate_transactions_root(),
+            Ok(block.transactions_root)
+        );
+        let json = serde_json::to_string(&block).unwrap();
+        assert!(json.contains(&format!("\"{}\"", block.hash)));
+        let decoded: Block = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded.calculate_hash(), Ok(block.hash));
+
+        let mut tampered = block.clone();
+        tampered.transactions[0].fee += 1.0;
+        assert_ne!(
+            tampered.calculate_transactions_root(),
+            Ok(block.transactions_root)
+        );
+
+        let nan_fee = Transaction::new(
+            "Alice".to_string(),
+            TransactionType::Stake {
+                user: "Alice".to_string(),
+                amount: 1.0,
+            },
+            f64::NAN,
+        );
+        assert!(blockchain.add_block(vec![nan_fee.clone()]).is_err());
+
+        // A peer's block holding it is rejected instead of hashed
+        let mut imported = block.clone();
+        imported.previous_hash = block.hash;
+        imported.transactions = vec![nan_fee];
+        assert!(blockchain.process_block(imported).is_err());
+        assert!(blockchain.wallets.stake("Alice", f64::NAN, 0.0).is_err());
+        assert!(blockchain
+            .wallets
+            .unstake("Alice", 0, 0.0, f64::NAN)
+            .is_err());
+        assert!(blockchain.wallets.charge_fee("Alice", -1.0).is_err());
+        let (state_root, _) = state_root::compute(&blockchain.state_entries()).unwrap();
+        assert_eq!(state_root, block.state_root);
+    }
+
+    #[test]
+    fn test_inclusion_proof_verifies_against_header() {
+        let mut blockchain = new_blockchain();
+        let transfer = |receiver: &str, amount: f64| {
+            Transaction::new(
+                static_config::GENESIS.to_string(),
+                TransactionType::Transfer {
+                    sender: static_config::GENESIS.to_string(),
+                    receiver: receiver.to_string(),
+                    amount,
+                },
+                0.0,
+            )
+        };
+        let transactions = vec![
+            transfer("Alice", 1.0),
+            transfer("Bob", 2.0),
+            transfer("Carol", 3.0),
+        ];
+        blockchain.add_block(transactions.clone()).unwrap();
+        let height = blockchain.get_height();
+        let header = blockchain.chain.get_block_by_idx(height).unwrap().clone();
+
+        for (index, tx) in transactions.iter().enumerate() {
+            let proof = blockchain
+                .get_inclusion_proof(height, &tx.hash().unwrap())
+                .unwrap();
+            assert_eq!(proof.index, index);
+            assert!(proof.verify(&tx.hash().unwrap(), &header.transactions_root));
+            assert!(!proof.verify(
+                &transfer("Dave", 1.0).hash().unwrap(),
+                &header.transactions_root
+            ));
+        }
+        // The index decides the side of each sibling, so it cannot be forged
+        let mut moved = blockchain
+            .get_inclusion_proof(height, &transactions[1].hash().unwrap())
+            .unwrap();
+        moved.index = 0;
+        assert!(!moved.verify(&transactions[1].hash().unwrap(), &header.transactions_root));
+        // Nor can an inner node be proven as a transaction
+        let leaves: Vec<Hash> = transactions.iter().map(|tx| tx.hash().unwrap()).collect();
+        let inner_node = Hash::combine(&Hash::leaf(&leaves[0]), &Hash::leaf(&leaves[1]));
+        let forged = InclusionProof {
+            index: 0,
+            leaf_count: 2,
+            siblings: vec![Hash::leaf(&leaves[2])],
+        };
+        assert!(!forged.verify(&inner_node, &header.transactions_root));
+
+        let missing = transfer("Alice", 4.0).hash().unwrap();
+        assert!(matches!(
+            blockchain.get_inclusion_proof(height, &missing),
+            Err(BChainError::TransactionNotIncluded(_, hash)) if hash == missing
+        ));
+        assert!(blockchain
+            .get_inclusion_proof(height + 1, &transactions[0].hash().unwrap())
+            .is_err());
+    }
+
+    #[test]
+    fn test_block_timestamps_follow_clock_and_rules() {
+        let slot_start =
+            |slot: i64| static_config::GENESIS_TIMESTAMP + slot * static_config::SLOT_DURATION;
+        let clock = Arc::new(clock::ManualClock::new(slot_start(20)));
+        let mut blockchain = Blockchain::with_clock(clock.clone());
+        assert_eq!(blockchain.current_slot(), Some(20));
+
+        blockchain.add_block(vec![]).unwrap();
+        let timestamp =
+            |blockchain: &Blockchain| blockchain.chain.get_last_block().unwrap().timestamp;
+        assert_eq!(timestamp(&blockchain), slot_start(20));
+        // Slot 20 holds a block until its last second
+        clock.set(slot_start(21) - 1);
+        assert!(blockchain.add_block(vec![]).is_err());
+        clock.set(slot_start(21));
+        blockchain.add_block(vec![]).unwrap();
+        assert_eq!(timestamp(&blockchain), slot_start(21));
+
+        let import = |blockchain: &mut Blockchain, timestamp: i64| {
+            let tip = blockchain.chain.get_last_block().unwrap();
+            let block = Block::new(
+                timestamp,
+                vec![],
+                tip.hash,
+                tip.validator.clone(),
+                tip.state_root,
+                Bloom::default(),
+            )
+            .unwrap();
+            blockchain.process_block(block)
+        };
+        // Imported blocks start after the window of their parent's slot
+        assert!(import(&mut blockchain, slot_start(22) - 1).is_err());
+        assert!(import(&mut blockchain, slot_start(22)).is_ok());
+        // and end within the allowed drift from the local clock
+        let latest = clock.now() + static_config::MAX_FUTURE_BLOCK_TIME;
+        assert!(import(&mut blockchain, latest + 1).is_err());
+        assert!(import(&mut blockchain, latest).is_ok());
+        assert!(import(&mut blockchain, latest).is_err());
+        assert!(blockchain.is_valid());
+    }
+
+    #[test]
+    fn test_transactions_are_indexed_by_hash_and_account() {
+        let mut blockchain = new_blockchain();
+        initiate_account(&mut blockchain, "Alice".to_string());
+        let payment = Transaction::new(
+            "Alice".to_string(),
+            TransactionType::Transfer {
+                sender: "Alice".to_string(),
+                receiver: "Bob".to_string(),
+                amount: 1.0,
+            },
+            0.0,
+        );
+        blockchain
+            .add_block(vec![payment.clone(), payment.clone()])
+            .unwrap();
+        put_stake(&mut blockchain, "Alice".to_string(), 10.0).unwrap();
+        let height = blockchain.get_height();
+
+        let paid = [
+            TxLocation {
+                height: height - 1,
+                index: 0,
+            },
+            TxLocation {
+                height: height - 1,
+                index: 1,
+            },
+        ];
+        assert_eq!(
+            blockchain.get_transaction_locations(&payment.hash().unwrap()),
+            paid
+        );
+        assert_eq!(blockchain.get_account_history("Bob", 0, 10), paid);
+        let alice = blockchain.get_account_history("Alice", 0, 10).to_vec();
+        assert_eq!(alice.len(), 4);
+        assert_eq!(alice[3], TxLocation { height, index: 0 });
+        assert_eq!(blockchain.get_account_history("Alice", 1, 2), &alice[1..3]);
+        assert!(blockchain.get_account_history("Alice", 5, 2).is_empty());
+
+        blockchain.rebuild_indexes();
+        assert_eq!(blockchain.get_account_history("Alice", 0, 10), alice);
+        assert!(blockchain.get_account_history("Carol", 0, 10).is_empty());
+    }
+
+    #[test]
+    fn test_pruned_node_keeps_recent_and_snapshot_states() {
+        let mut blockchain = new_blockchain();
+        blockchain.set_state_retention(StateRetention::Archive);
+        initiate_account(&mut blockchain, "Alice".to_string());
+        let mut wallets = vec![None, Some(blockchain.get_wallet("Alice").unwrap().clone())];
+        while blockchain.get_height() < static_config::SNAPSHOT_INTERVAL + 4 {
+            put_stake(&mut blockchain, "Alice".to_string(), 1.0).unwrap();
+            wallets.push(Some(blockchain.get_wallet("Alice").unwrap().clone()));
+        }
+        let tip = blockchain.get_height();
+        for (height, wallet) in wallets.iter().enumerate() {
+            assert_eq!(
+                blockchain
+                    .get_wallet_at("Alice", height)
+                    .ok()
+                    .map(|(w, _)| w),
+                *wallet
+            );
+            let (state_root, _) =
+                state_root::compute(&blockchain.state_at(height).unwrap()).unwrap();
+            let block = blockchain.chain.get_block_by_idx(height).unwrap();
+            assert_eq!(state_root, block.state_root);
+        }
+        assert!(matches!(
+            blockchain.get_wallet_at("Alice", tip + 1),
+            Err(BChainError::BlockNotFound(_))
+        ));
+
+        blockchain.set_state_retention(StateRetention::Pruned { recent_blocks: 3 });
+        for (height, wallet) in wallets.iter().enumerate().skip(tip - 2) {
+            assert_eq!(
+                blockchain
+                    .get_wallet_at("Alice", height)
+                    .ok()
+                    .map(|(w, _)| w),
+                *wallet
+            );
+        }
+        let snapshot_height = static_config::SNAPSHOT_INTERVAL;
+        assert_eq!(
+            blockchain
+                .get_wallet_at("Alice", snapshot_height)
+                .ok()
+                .map(|(w, _)| w),
+            wallets[snapshot_height]
+        );
+        assert!(matches!(
+            blockchain.get_wallet_at("Alice", snapshot_height - 1),
+            Err(BChainError::StatePruned(_))
+        ));
+        assert!(matches!(
+            blockchain.get_wallet_at("Alice", tip - 3),
+            Err(BChainError::StatePruned(_))
+        ));
+
+        put_stake(&mut blockchain, "Alice".to_string(), 1.0).unwrap();
+        assert!(blockchain.get_wallet_at("Alice", tip - 2).is_err());
+        assert_eq!(
+            blockchain.get_wallet_at("Alice", tip + 1).unwrap().0,
+            *blockchain.get_wallet("Alice").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_historical_wallet_is_proven_against_state_root() {
+        let mut blockchain = new_blockchain();
+        initiate_account(&mut blockchain, "Alice".to_string());
+        transfer(
+            &mut blockchain,
+            "Alice".to_string(),
+            "Bob".to_string(),
+            30.0,
+        )
+        .unwrap();
+        let entry = |user: &str, wallet: Wallet| StateEntry::Wallet {
+            user: user.to_string(),
+            wallet,
+        };
+
+        let (wallet, proof) = blockchain.get_wallet_at("Alice", 1).unwrap();
+        assert_eq!(wallet.balance, INITIAL_AMOUNT);
+        let state_root = blockchain.chain.get_block_by_idx(1).unwrap().state_root;
+        assert!(proof.verify(&entry("Alice", wallet.clone()), &state_root));
+        let latest_root = blockchain.chain.get_last_block().unwrap().state_root;
+        assert!(!proof.verify(&entry("Alice", wallet), &latest_root));
+
+        let (mut wallet, proof) = blockchain.get_wallet_at("Bob", 2).unwrap();
+        assert!(proof.verify(&entry("Bob", wallet.clone()), &latest_root));
+        wallet.balance += 1.0;
+        assert!(!proof.verify(&entry("Bob", wallet), &latest_root));
+        assert!(matches!(
+            blockchain.get_wallet_at("Bob", 1),
+            Err(BChainError::UserNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_batch_pays_one_fee_and_reports_each_operation() {
+        let mut blockchain = new_blockchain();
+        initiate_account(&mut blockchain, "Alice".to_string());
+        let pay = |receiver: &str, amount: f64| TransactionType::Transfer {
+            sender: "Alice".to_string(),
+            receiver: receiver.to_string(),
+            amount,
+        };
+        let stake = TransactionType::Stake {
+            user: "Alice".to_string(),
+            amount: 5.0,
+        };
+        let submit = |blockchain: &mut Blockchain,
+                      operations: Vec<TransactionType>,
+                      mode: BatchMode| {
+            let batch = TransactionType::Batch { operations, mode };
+            blockchain.add_block(vec![Transaction::new("Alice".to_string(), batch, 1.0)])?;
+            Ok::<_, String>(blockchain.get_receipts(blockchain.get_height()).unwrap()[0].clone())
+        };
+
+        let receipt = submit(
+            &mut blockchain,
+            vec![pay("Bob", 10.0), pay("Carol", 500.0), stake.clone()],
+            BatchMode::BestEffort,
+        )
+        .unwrap();
+        assert_eq!(receipt.exit_code, 0);
+        let exit_codes: Vec<i32> = receipt.operations.iter().map(|r| r.exit_code).collect();
+        assert_eq!(exit_codes, vec![0, -1, 0]);
+
+        let receipt = submit(
+            &mut blockchain,
+            vec![pay("Bob", 10.0), pay("Carol", 500.0), stake.clone()],
+            BatchMode::AllOrNothing,
+        )
+        .unwrap();
+        assert_eq!(receipt.exit_code, -1);
+        assert!(receipt.error.unwrap().starts_with("Operation 1 failed"));
+        assert_eq!(receipt.operations.len(), 2);
+
+        let alice = blockchain.get_wallet("Alice").unwrap();
+        assert_eq!((alice.balance, alice.staked), (INITIAL_AMOUNT - 17.0, 5.0));
+        assert_eq!(blockchain.get_wallet("Bob").unwrap().balance, 10.0);
+        assert!(blockchain.get_wallet("Carol").is_err());
+
+        let foreign = TransactionType::Stake {
+            user: "Bob".to_string(),
+            amount: 1.0,
+        };
+        assert!(submit(&mut blockchain, vec![foreign], BatchMode::BestEffort).is_err());
+        let nested = TransactionType::Batch {
+            operations: vec![stake],
+            mode: BatchMode::BestEffort,
+        };
+        assert!(submit(&mut blockchain, vec![nested], BatchMode::BestEffort).is_err());
+        assert_eq!(blockchain.get_account_history("Bob", 0, 10).len(), 2);
+    }
+
+    #[test]
+    fn test_node_syncs_from_snapshot_and_follows_chain() {
+        let mut blockchain = new_blockchain();
+        let users: Vec<String> = (0..20).map(|i| format!("User{}", i)).collect();
+        let transfers = users
+            .iter()
+            .map(|user| {
+                Transaction::new(
+                    static_config::GENESIS.to_string(),
+                    TransactionType::Transfer {
+                        sender: static_config::GENESIS.to_string(),
+                        receiver: user.clone(),
+                        amount: 1.0,
+                    },
+                    0.0,
+                )
+            })
+            .collect();
+        blockchain.add_block(transfers).unwrap();
+        while blockchain.get_height() < static_config::SNAPSHOT_INTERVAL + 2 {
+            put_stake(&mut blockchain, "User1".to_string(), 0.05).unwrap();
+        }
+
+        let manifest = blockchain.get_snapshot_manifest().unwrap();
+        assert_eq!(manifest.height, static_config::SNAPSHOT_INTERVAL);
+        assert_eq!(manifest.chunk_count, 2);
+        let mut sync = snapshot::SnapshotSync::new(manifest.clone()).unwrap();
+        let mut tampered = blockchain.get_snapshot_chunk(manifest.height, 0).unwrap();
+        if let StateEntry::Wallet { wallet, .. } = &mut tampered.entries[0].0 {
+            wallet.balance += 1.0;
+        }
+        assert!(sync.add_chunk(tampered).is_err());
+        // Peer data that cannot be hashed is rejected, not a panic
+        let mut unhashable = blockchain.get_snapshot_chunk(manifest.height, 0).unwrap();
+        if let StateEntry::Wallet { wallet, .. } = &mut unhashable.entries[0].0 {
+            wallet.balance = f64::NAN;
+        }
+        assert!(matches!(
+            sync.add_chunk(unhashable),
+            Err(BChainError::InvalidSnapshot(_))
+        ));
+        let mut unhashable = manifest.clone();
+        unhashable.recent_blocks[0].total_fees = f64::NAN;
+        assert!(matches!(
+            snapshot::SnapshotSync::new(unhashable),
+            Err(BChainError::InvalidSnapshot(_))
+        ));
+        for index in [1, 0] {
+            let chunk = blockchain
+                .get_snapshot_chunk(manifest.height, index)
+                .unwrap();
+            sync.add_chunk(chunk).unwrap();
+        }
+        assert!(sync.missing_chunks().is_empty());
+        let mut synced = sync.finish(Arc::new(SystemClock)).unwrap();
+        assert_eq!(synced.get_height(), manifest.height);
+
+        for height in manifest.height + 1..=blockchain.get_height() {
+            let block = blockchain.chain.get_block_by_idx(height).unwrap().clone();
+            synced.process_block(block).unwrap();
+        }
+        assert_eq!(
+            synced.chain.get_last_block().unwrap().hash,
+            blockchain.chain.get_last_block().unwrap().hash
+        );
+        assert_eq!(
+            synced.get_wallet("User1").unwrap(),
+            blockchain.get_wallet("User1").unwrap()
+        );
+        assert!(synced.is_valid());
+
+        // A block is imported only if its transactions rebuild its state
+        // root, and the transactions of a rejected block are rolled back
+        put_stake(&mut blockchain, "User1".to_string(), 0.05).unwrap();
+        let mut forged = blockchain.chain.get_last_block().unwrap().clone();
+        forged.state_root = Hash::digest(b"forged");
+        forged.hash = forged.calculate_hash().unwrap();
+        let wallets = synced.wallets.wallets.clone();
+        let (state_root, _) = state_root::compute(&synced.state_entries()).unwrap();
+        assert!(synced.process_block(forged).is_err());
+        assert_eq!(synced.get_height(), blockchain.get_height() - 1);
+        assert_eq!(synced.wallets.wallets, wallets);
+        assert_eq!(
+            state_root::compute(&synced.state_entries()).unwrap().0,
+            state_root
+        );
+
+        let incomplete = snapshot::SnapshotSync::new(manifest).unwrap();
+        assert!(incomplete.finish(Arc::new(SystemClock)).is_err());
+    }
 }
@@ -1,6 +1,15 @@
 use crate::bchain_error::BChainError;
 use crate::primitives::Block;
+use crate::primitives::Event;
+use crate::primitives::Hash;
+use crate::primitives::InclusionProof;
+use crate::primitives::Receipt;
+use crate::primitives::SnapshotChunk;
+use crate::primitives::SnapshotManifest;
+use crate::primitives::StateProof;
+use crate::primitives::Trace;
 use crate::primitives::Transaction;
+use crate::primitives::TxLocation;
 use crate::primitives::Wallet;
 use serde::{Deserialize, Serialize};
 
@@ -20,4 +29,62 @@ pub struct Message {
 pub trait BlockchainFacade {
     fn receive(&mut self, msg: Message) -> Result<(), BChainError>;
     fn get_wallet(&self, user: &str) -> Result<&Wallet, BChainError>;
+    /// Wallet as it was after the block at `height`, with its proof against
+    /// that block's `state_root`. Pruned nodes only keep the state of recent
+    /// blocks and of their snapshot.
+    fn get_wallet_at(&self, user: &str, height: usize)
+        -> Result<(Wallet, StateProof), BChainError>;
+    /// Value a contract stored under `key` after the block at `height`, with
+    /// its proof against that block's `state_root`
+    fn get_contract_storage_at(
+        &self,
+        contract_address: &str,
+        key: &str,
+        height: usize,
+    ) -> Result<(Vec<u8>, StateProof), BChainError>;
+    /// Runs `method` of a contract without a transaction. Host functions that
+    /// change state fail and any state is rolled back, so the query is free.
+    fn query_contract(
+        &mut self,
+        contract_address: &str,
+        method: &str,
+        input: &[u8],
+    ) -> Result<Receipt, BChainError>;
+    /// Re-executes transaction `index` of the block at `height` against the
+    /// state the block started from and returns the trace of its contract call
+    fn trace_transaction(&self, height: usize, index: usize) -> Result<Trace, BChainError>;
+    /// Proof that the transaction with `tx_hash` is part of the block at
+    /// `height`, verifiable against that block's `transactions_root`
+    fn get_inclusion_proof(
+        &self,
+        height: usize,
+        tx_hash: &Hash,
+    ) -> Result<InclusionProof, BChainError>;
+    /// Every position of a transaction with `tx_hash`. Transactions carry no
+    /// nonce, so identical ones submitted twice share a hash.
+    fn get_transaction_locations(&self, tx_hash: &Hash) -> &[TxLocation];
+    /// Up to `limit` of the transactions touching `account`, oldest first,
+    /// after skipping the first `offset`
+    fn get_account_history(&self, account: &str, offset: usize, limit: usize) -> &[TxLocation];
+    /// Snapshot of the state at the last snapshot height, which a new node
+    /// can sync from instead of replaying the chain
+    fn get_snapshot_manifest(&self) -> Result<SnapshotManifest, BChainError>;
+    /// Chunk `index` of the snapshot at `height`, with a proof of every
+    /// entry against the `state_root` of the block at that height
+    fn get_snapshot_chunk(&self, height: usize, index: usize)
+        -> Result<SnapshotChunk, BChainError>;
+    /// Value stored by a contract under `key`
+    fn get_storage(&self, contract_address: &str, key: &str) -> Option<&Vec<u8>>;
+    /// Height of the last block, 0 for genesis
+    fn get_height(&self) -> usize;
+    fn get_receipts(&self, block_height: usize) -> Result<&Vec<Receipt>, BChainError>;
+    /// Events of `contract_address` with the given topic (any if None) emitted
+    /// in blocks `from_height..=to_height`, paired with their block height
+    fn get_events(
+        &self,
+        contract_address: &str,
+        topic: Option<&[u8; 32]>,
+        from_height: usize,
+        to_height: usize,
+    ) -> Vec<(usize, Event)>;
 }
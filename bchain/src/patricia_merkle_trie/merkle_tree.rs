@@ -0,0 +1,84 @@
+use crate::primitives::Hash;
+
+/// Levels of the binary Merkle tree over `leaves`, from the leaf nodes up to
+/// the root. A node without a sibling is carried up unchanged.
+pub fn build(leaves: Vec<Hash>) -> Vec<Vec<Hash>> {
+    let mut tree = vec![leaves.iter().map(Hash::leaf).collect::<Vec<_>>()];
+    while tree.last().unwrap().len() > 1 {
+        let next_level = tree
+            .last()
+            .unwrap()
+            .chunks(2)
+            .map(|chunk| match chunk {
+                [left, right] => Hash::combine(left, right),
+                _ => chunk[0],
+            })
+            .collect();
+        tree.push(next_level);
+    }
+    tree
+}
+
+/// Root of the tree over `leaves`, the digest of nothing if there are none
+pub fn root(leaves: Vec<Hash>) -> Hash {
+    match build(leaves).last().unwrap().first() {
+        Some(root) => *root,
+        None => Hash::digest(&[]),
+    }
+}
+
+/// Siblings on the path from leaf `index` of `tree` to the root, each paired
+/// with whether the path goes through the left child
+pub fn proof(tree: &[Vec<Hash>], index: usize) -> Vec<(Hash, bool)> {
+    let mut proof = Vec::new();
+    let mut idx = index;
+    for level in &tree[..tree.len() - 1] {
+        let is_left = idx.is_multiple_of(2);
+        let sibling_idx = if is_left { idx + 1 } else { idx - 1 };
+        if sibling_idx < level.len() {
+            proof.push((level[sibling_idx], is_left))
+        }
+        idx /= 2; //Move up to parent
+    }
+    proof
+}
+
+/// Recomputes the root from a leaf hash and the siblings on its path
+pub fn root_from_proof(leaf_hash: Hash, proof: &[(Hash, bool)]) -> Hash {
+    let mut current_hash = Hash::leaf(&leaf_hash);
+    for (sibling, is_left) in proof {
+        current_hash = if *is_left {
+            Hash::combine(&current_hash, sibling)
+        } else {
+            Hash::combine(sibling, &current_hash)
+        };
+    }
+    current_hash
+}
+
+/// Recomputes the root from leaf `index` of a tree over `leaf_count` leaves
+/// and the siblings on its path. Which side each sibling is on follows from
+/// `index`, None if the siblings do not fit the path.
+pub fn root_from_index(
+    leaf_hash: Hash,
+    index: usize,
+    leaf_count: usize,
+    siblings: &[Hash],
+) -> Option<Hash> {
+    if index >= leaf_count {
+        return None;
+    }
+    let mut siblings = siblings.iter();
+    let mut current_hash = Hash::leaf(&leaf_hash);
+    let (mut idx, mut level_len) = (index, leaf_count);
+    while level_len > 1 {
+        if !idx.is_multiple_of(2) {
+            current_hash = Hash::combine(siblings.next()?, &current_hash);
+        } else if idx + 1 < level_len {
+            current_hash = Hash::combine(&current_hash, siblings.next()?);
+        }
+        idx /= 2;
+        level_len = level_len.div_ceil(2);
+    }
+    siblings.next().is_none().then_some(current_hash)
+}
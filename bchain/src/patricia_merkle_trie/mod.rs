@@ -1,2 +1,3 @@
+pub mod merkle_tree;
 pub mod pmt_proof;
 pub mod state_root;
@@ -1,40 +1,65 @@
+use crate::patricia_merkle_trie::merkle_tree;
+use crate::primitives::{Contract, Hash, StateEntry};
+use crate::validators::TwoEpochValidators;
 use crate::wallets::Wallets;
-use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 
-pub fn compute(wallets: &Wallets) -> (String, Vec<Vec<String>>) {
-    if wallets.wallets.is_empty() {
-        let zero_hash = format!("{:x}", Sha256::new().finalize());
-        return (zero_hash, vec![]);
-    }
-
-    let mut leaves: Vec<(String, String)> = wallets
-        .wallets
+/// Leaves of the state tree, in order
+pub fn entries(
+    wallets: &Wallets,
+    contracts: &HashMap<String, Contract>,
+    contract_storage: &HashMap<String, HashMap<String, Vec<u8>>>,
+    validators: &TwoEpochValidators,
+) -> Vec<StateEntry> {
+    let mut users: Vec<&String> = wallets.wallets.keys().collect();
+    users.sort();
+    let mut addresses: Vec<&String> = contracts.keys().collect();
+    addresses.sort();
+    let mut storage: Vec<(&String, &String, &Vec<u8>)> = contract_storage
         .iter()
-        .map(|(user, wallet)| {
-            let data = format!("{}{}", user, serde_json::to_string(wallet).unwrap());
-            let mut hasher = Sha256::new();
-            hasher.update(data);
-            (user.clone(), format!("{:x}", hasher.finalize()))
+        .flat_map(|(address, storage)| {
+            storage
+                .iter()
+                .map(move |(key, value)| (address, key, value))
         })
         .collect();
-    leaves.sort_by(|a, b| a.0.cmp(&b.0));
-    let mut tree: Vec<Vec<String>> = vec![leaves.iter().map(|(_, h)| h.clone()).collect()];
+    storage.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+
+    let wallets = users.into_iter().map(|user| StateEntry::Wallet {
+        user: user.clone(),
+        wallet: wallets.wallets[user].clone(),
+    });
+    let contracts = addresses.into_iter().map(|address| StateEntry::Contract {
+        address: address.clone(),
+        contract: contracts[address].clone(),
+    });
+    let storage = storage
+        .into_iter()
+        .map(|(address, key, value)| StateEntry::Storage {
+            address: address.clone(),
+            key: key.clone(),
+            value: value.clone(),
+        });
+    let validators = StateEntry::Validators {
+        current: validators.get_current_epoch_validators().clone(),
+        next: validators.get_next_epoch_validators().clone(),
+    };
+    wallets
+        .chain(contracts)
+        .chain(storage)
+        .chain([validators])
+        .collect()
+}
 
-    let mut current_level = tree[0].clone();
-    while current_level.len() > 1 {
-        let mut next_level = Vec::new();
-        for chunk in current_level.chunks(2) {
-            let combined = if chunk.len() == 2 {
-                format!("{}{}", chunk[0], chunk[1])
-            } else {
-                chunk[0].to_string()
-            };
-            let mut hasher = Sha256::new();
-            hasher.update(&combined);
-            next_level.push(format!("{:x}", hasher.finalize()));
-        }
-        tree.push(next_level.clone());
-        current_level = next_level;
+/// Root and tree of `entries`. Fails if an entry has no canonical encoding.
+pub fn compute(entries: &[StateEntry]) -> Result<(Hash, Vec<Vec<Hash>>), String> {
+    if entries.is_empty() {
+        return Ok((Hash::digest(&[]), vec![]));
     }
-    (current_level[0].clone(), tree)
+    let tree = merkle_tree::build(entries.iter().map(leaf_hash).collect::<Result<_, _>>()?);
+    Ok((tree.last().unwrap()[0], tree))
+}
+
+pub fn leaf_hash(entry: &StateEntry) -> Result<Hash, String> {
+    Hash::of(entry)
 }
@@ -1,54 +1,88 @@
-use crate::primitives::Transaction;
-use chrono::Utc;
+use crate::patricia_merkle_trie::merkle_tree;
+use crate::primitives::{Bloom, Hash, InclusionProof, Transaction};
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Block {
     pub timestamp: i64,
     pub transactions: Vec<Transaction>,
-    pub previous_hash: String,
-    pub hash: String,
+    pub previous_hash: Hash,
+    pub hash: Hash,
     pub validator: String,
-    pub state_root: String,
+    pub state_root: Hash,
+    /// Root of the Merkle tree over the hashes of the transactions
+    pub transactions_root: Hash,
     pub total_fees: f64,
+    pub logs_bloom: Bloom,
 }
 
 impl Block {
     pub fn new(
+        timestamp: i64,
         transactions: Vec<Transaction>,
-        previous_hash: String,
+        previous_hash: Hash,
         validator: String,
-        state_root: String,
-    ) -> Self {
-        let timestamp = Utc::now().timestamp();
+        state_root: Hash,
+        logs_bloom: Bloom,
+    ) -> Result<Self, String> {
         let total_fees = transactions.iter().map(|tx| tx.fee).sum();
         let mut block = Block {
             timestamp,
             transactions,
             previous_hash,
-            hash: String::new(),
+            hash: Hash::default(),
             validator,
             state_root,
+            transactions_root: Hash::default(),
             total_fees,
+            logs_bloom,
         };
-        block.hash = block.calculate_hash();
-        block
+        block.transactions_root = block.calculate_transactions_root()?;
+        block.hash = block.calculate_hash()?;
+        Ok(block)
     }
 
-    pub fn calculate_hash(&self) -> String {
-        let input = format!(
-            "{}{}{}{}{}{}",
+    /// Hash of the header, which commits to the transactions through their
+    /// root. Fails when the header does not have a canonical encoding.
+    pub fn calculate_hash(&self) -> Result<Hash, String> {
+        Hash::of(&(
             self.timestamp,
-            serde_json::to_string(&self.transactions).unwrap(),
-            self.previous_hash,
-            self.validator,
-            self.state_root,
+            &self.previous_hash,
+            &self.validator,
+            &self.state_root,
+            &self.transactions_root,
             self.total_fees,
-        );
-        let mut hasher = Sha256::new();
-        hasher.update(input);
-        let result = hasher.finalize();
-        format!("{:x}", result)
+            &self.logs_bloom,
+        ))
+    }
+
+    pub fn calculate_transactions_root(&self) -> Result<Hash, String> {
+        let leaves = self
+            .transactions
+            .iter()
+            .map(Transaction::hash)
+            .collect::<Result<_, _>>()?;
+        Ok(merkle_tree::root(leaves))
+    }
+
+    /// Proof that the transaction with `tx_hash` is part of the block
+    pub fn inclusion_proof(&self, tx_hash: &Hash) -> Option<InclusionProof> {
+        let leaves: Vec<Hash> = self
+            .transactions
+            .iter()
+            .map(Transaction::hash)
+            .collect::<Result<_, _>>()
+            .ok()?;
+        let index = leaves.iter().position(|hash| hash == tx_hash)?;
+        let leaf_count = leaves.len();
+        let tree = merkle_tree::build(leaves);
+        Some(InclusionProof {
+            index,
+            leaf_count,
+            siblings: merkle_tree::proof(&tree, index)
+                .into_iter()
+                .map(|(sibling, _)| sibling)
+                .collect(),
+        })
     }
 }
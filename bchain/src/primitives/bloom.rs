@@ -0,0 +1,54 @@
+use borsh::BorshSerialize;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt;
+
+const BLOOM_BYTES: usize = 256;
+const HASHES_PER_ITEM: usize = 3;
+
+/// 2048-bit bloom filter over the contract addresses and topics of the events
+/// of a block. A negative answer is definite, a positive one must be confirmed
+/// against the receipts.
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bloom {
+    bits: Vec<u8>,
+}
+
+impl Default for Bloom {
+    fn default() -> Self {
+        Self {
+            bits: vec![0; BLOOM_BYTES],
+        }
+    }
+}
+
+impl Bloom {
+    pub fn accrue(&mut self, item: &[u8]) {
+        for (byte, mask) in Self::positions(item) {
+            self.bits[byte] |= mask;
+        }
+    }
+
+    pub fn contains(&self, item: &[u8]) -> bool {
+        Self::positions(item).all(|(byte, mask)| self.bits[byte] & mask != 0)
+    }
+
+    /// Each item sets 3 bits, indexed by 11-bit chunks of its hash
+    fn positions(item: &[u8]) -> impl Iterator<Item = (usize, u8)> {
+        let hash = Sha256::digest(item);
+        (0..HASHES_PER_ITEM).map(move |i| {
+            let bit =
+                u16::from_be_bytes([hash[2 * i], hash[2 * i + 1]]) as usize % (BLOOM_BYTES * 8);
+            (bit / 8, 1 << (bit % 8))
+        })
+    }
+}
+
+impl fmt::Display for Bloom {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.bits {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,21 @@
+use borsh::BorshSerialize;
+use serde::{Deserialize, Serialize};
+
+/// Virtual machine that runs a contract's code.
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Vm {
+    /// WebAssembly module exporting one function per method
+    #[default]
+    Wasm,
+    /// EVM bytecode, deployed by running its creation code
+    Evm,
+}
+
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Contract {
+    pub code: Vec<u8>,
+    /// Account allowed to upgrade and destroy the contract, the deployer by default
+    pub admin: String,
+    #[serde(default)]
+    pub vm: Vm,
+}
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Log entry emitted by a contract through the `emit_event` host function.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Event {
+    pub contract_address: String,
+    pub topics: Vec<[u8; 32]>,
+    pub data: Vec<u8>,
+}
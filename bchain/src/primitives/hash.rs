@@ -0,0 +1,93 @@
+use borsh::BorshSerialize;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the canonical encoding. It prefixes everything hashed through
+/// `Hash::of`, so hashes from different encodings never collide.
+pub const ENCODING_VERSION: u8 = 1;
+
+/// Prefixes of the leaves and of the inner nodes of Merkle trees, so a leaf
+/// never passes for an inner node or the other way around
+const MERKLE_LEAF_PREFIX: u8 = 0;
+const MERKLE_NODE_PREFIX: u8 = 1;
+
+/// Canonical binary encoding of a value: borsh, with fixed-size little-endian
+/// integers and floats, length-prefixed sequences and one-byte enum tags.
+/// NaN has no canonical encoding and is rejected.
+pub fn encode<T: BorshSerialize>(value: &T) -> Result<Vec<u8>, String> {
+    let mut bytes = vec![ENCODING_VERSION];
+    value.serialize(&mut bytes).map_err(|e| e.to_string())?;
+    Ok(bytes)
+}
+
+/// Sha256 digest, rendered as lowercase hex.
+#[derive(BorshSerialize, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Hash(pub [u8; 32]);
+
+impl Hash {
+    pub fn digest(data: &[u8]) -> Self {
+        Self(Sha256::digest(data).into())
+    }
+
+    /// Hash of the canonical encoding of `value`, which fails for NaN
+    pub fn of<T: BorshSerialize>(value: &T) -> Result<Self, String> {
+        encode(value).map(|bytes| Self::digest(&bytes))
+    }
+
+    /// Node of a Merkle tree holding the leaf `hash`
+    pub fn leaf(hash: &Hash) -> Self {
+        Self::digest(&[&[MERKLE_LEAF_PREFIX][..], &hash.0].concat())
+    }
+
+    /// Parent of two nodes of a Merkle tree
+    pub fn combine(left: &Hash, right: &Hash) -> Self {
+        Self::digest(&[&[MERKLE_NODE_PREFIX][..], &left.0, &right.0].concat())
+    }
+}
+
+impl fmt::Display for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for byte in &self.0 {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Hash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Hash({})", self)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = String;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(format!("Invalid hash: {}", hex));
+        }
+        let mut hash = [0; 32];
+        for (i, byte) in hash.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+                .map_err(|_| format!("Invalid hash: {}", hex))?;
+        }
+        Ok(Self(hash))
+    }
+}
+
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
@@ -0,0 +1,23 @@
+use crate::patricia_merkle_trie::merkle_tree;
+use crate::primitives::Hash;
+use serde::{Deserialize, Serialize};
+
+/// Proof that a transaction is part of a block, checked against the
+/// `transactions_root` of the block's header alone.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct InclusionProof {
+    /// Position of the transaction in the block
+    pub index: usize,
+    /// Number of transactions in the block, which fixes the shape of the tree
+    pub leaf_count: usize,
+    /// Siblings on the path to the root, from the leaves up. Their side
+    /// follows from `index`.
+    pub siblings: Vec<Hash>,
+}
+
+impl InclusionProof {
+    pub fn verify(&self, tx_hash: &Hash, transactions_root: &Hash) -> bool {
+        merkle_tree::root_from_index(*tx_hash, self.index, self.leaf_count, &self.siblings)
+            == Some(*transactions_root)
+    }
+}
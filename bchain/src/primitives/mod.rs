@@ -1,7 +1,27 @@
 mod block;
+mod bloom;
+mod contract;
+mod event;
+mod hash;
+mod inclusion_proof;
+mod receipt;
+mod snapshot;
+mod state_proof;
+mod trace;
 mod transaction;
+mod tx_location;
 mod wallet;
 
 pub use block::*;
+pub use bloom::*;
+pub use contract::*;
+pub use event::*;
+pub use hash::*;
+pub use inclusion_proof::*;
+pub use receipt::*;
+pub use snapshot::*;
+pub use state_proof::*;
+pub use trace::*;
 pub use transaction::*;
+pub use tx_location::*;
 pub use wallet::*;
@@ -0,0 +1,24 @@
+use crate::primitives::Event;
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a single transaction, stored alongside the block that included it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Receipt {
+    pub exit_code: i32,
+    pub return_data: Vec<u8>,
+    pub gas_used: u64,
+    pub error: Option<String>,
+    /// Address assigned by a contract deployment or affected by an upgrade
+    pub contract_address: Option<String>,
+    /// Hash of the code installed by a deployment or an upgrade
+    pub code_hash: Option<String>,
+    /// Events emitted by the call and the nested calls that succeeded
+    pub events: Vec<Event>,
+    /// Accounts the call and the nested calls that succeeded paid coins to
+    #[serde(default)]
+    pub paid_accounts: Vec<String>,
+    /// Outcome of each operation of a batch, up to the one that failed an
+    /// all-or-nothing batch
+    #[serde(default)]
+    pub operations: Vec<Receipt>,
+}
@@ -0,0 +1,56 @@
+use crate::patricia_merkle_trie::merkle_tree;
+use crate::primitives::{Block, Contract, Hash, Wallet};
+use borsh::BorshSerialize;
+use serde::{Deserialize, Serialize};
+
+/// Leaf of the state tree. The leaves are ordered by variant and then by
+/// their keys, so the tree is the same for the same state.
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum StateEntry {
+    Wallet {
+        user: String,
+        wallet: Wallet,
+    },
+    Contract {
+        address: String,
+        contract: Contract,
+    },
+    Storage {
+        address: String,
+        key: String,
+        value: Vec<u8>,
+    },
+    Validators {
+        current: Vec<String>,
+        next: Vec<String>,
+    },
+}
+
+/// Describes the snapshot a node offers, enough to request and check its chunks
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotManifest {
+    pub height: usize,
+    /// Block at `height`, whose `state_root` the chunks are proven against
+    pub header: Block,
+    /// Blocks before `header`, oldest first, as many as block timestamp
+    /// rules and contracts look back
+    pub recent_blocks: Vec<Block>,
+    pub chunk_count: usize,
+}
+
+/// Consecutive state entries of a snapshot, each with its proof
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotChunk {
+    pub height: usize,
+    pub index: usize,
+    pub entries: Vec<(StateEntry, Vec<(Hash, bool)>)>,
+}
+
+impl SnapshotChunk {
+    pub fn verify(&self, state_root: &Hash) -> bool {
+        self.entries.iter().all(|(entry, proof)| {
+            Hash::of(entry)
+                .is_ok_and(|leaf| merkle_tree::root_from_proof(leaf, proof) == *state_root)
+        })
+    }
+}
@@ -0,0 +1,21 @@
+use crate::patricia_merkle_trie::merkle_tree;
+use crate::primitives::{Hash, StateEntry};
+use serde::{Deserialize, Serialize};
+
+/// Proof that an entry is part of the state after a block, checked against
+/// the `state_root` of the block's header alone.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StateProof {
+    /// Height of the block whose state holds the entry
+    pub height: usize,
+    /// Siblings on the path to the root, each paired with whether the path
+    /// goes through the left child
+    pub siblings: Vec<(Hash, bool)>,
+}
+
+impl StateProof {
+    pub fn verify(&self, entry: &StateEntry, state_root: &Hash) -> bool {
+        Hash::of(entry)
+            .is_ok_and(|leaf| merkle_tree::root_from_proof(leaf, &self.siblings) == *state_root)
+    }
+}
@@ -0,0 +1,83 @@
+use serde::{Deserialize, Serialize};
+
+/// Something a contract did while executed with tracing. `depth` is the call
+/// depth of the contract the step belongs to, 0 for the transaction's call.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum TraceStep {
+    /// A contract call started
+    Enter {
+        depth: usize,
+        contract_address: String,
+        method: String,
+        gas_limit: u64,
+    },
+    /// A host function returned. Steps caused by the host function, such as
+    /// the steps of a nested call, are recorded before it.
+    HostCall {
+        depth: usize,
+        name: String,
+        /// Argument names and values, without the blockchain pointer
+        args: Vec<(String, String)>,
+        result: String,
+        /// Gas charged for the host function, including nested calls
+        gas_used: u64,
+    },
+    StorageRead {
+        depth: usize,
+        contract_address: String,
+        key: String,
+        value: Option<Vec<u8>>,
+    },
+    StorageWrite {
+        depth: usize,
+        contract_address: String,
+        key: String,
+        before: Option<Vec<u8>>,
+        after: Option<Vec<u8>>,
+    },
+    /// A contract call finished, `trap` is set when the call trapped
+    Exit {
+        depth: usize,
+        exit_code: i32,
+        gas_used: u64,
+        trap: Option<String>,
+    },
+}
+
+/// Contract call on the stack at the time of a trap
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Frame {
+    pub contract_address: String,
+    pub method: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Trace {
+    pub steps: Vec<TraceStep>,
+}
+
+impl Trace {
+    /// Calls that were executing when the first trap happened, outermost
+    /// first, or an empty backtrace if nothing trapped
+    pub fn backtrace(&self) -> Vec<Frame> {
+        let mut stack = Vec::new();
+        for step in &self.steps {
+            match step {
+                TraceStep::Enter {
+                    contract_address,
+                    method,
+                    ..
+                } => stack.push(Frame {
+                    contract_address: contract_address.clone(),
+                    method: method.clone(),
+                }),
+                TraceStep::Exit { trap: Some(_), .. } => return stack,
+                TraceStep::Exit { .. } => {
+                    stack.pop();
+                }
+                _ => {}
+            }
+        }
+        Vec::new()
+    }
+}
@@ -1,6 +1,8 @@
+use crate::primitives::{Hash, Vm};
+use borsh::BorshSerialize;
 use serde::{Deserialize, Serialize};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone)]
 pub enum TransactionType {
     Transfer {
         sender: String,
@@ -17,13 +19,50 @@ pub enum TransactionType {
     },
     DeployContract {
         code: Vec<u8>,
+        /// Deploys to an address derived from the salt and code instead of the deployer's nonce
+        salt: Option<Vec<u8>>,
+        /// Virtual machine the code is written for
+        #[serde(default)]
+        vm: Vm,
     },
     CallContract {
         contract_address: String,
+        value: f64,
+        method: String,
+        input: Vec<u8>,
     },
+    /// Replaces the code of a contract keeping its storage and balance, `migrate`
+    /// names a method run once with the new code
+    UpgradeContract {
+        contract_address: String,
+        code: Vec<u8>,
+        migrate: Option<String>,
+    },
+    /// Removes a contract with its storage and returns its balance and storage
+    /// deposit to the admin
+    DestroyContract {
+        contract_address: String,
+    },
+    /// Runs `operations` in order as one transaction of the sender, paying
+    /// a single fee. Operations may only act for the sender.
+    Batch {
+        operations: Vec<TransactionType>,
+        #[serde(default)]
+        mode: BatchMode,
+    },
+}
+
+/// What a batch does when one of its operations fails
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BatchMode {
+    /// The failure reverts the whole batch, only the fee is kept
+    #[default]
+    AllOrNothing,
+    /// The failed operation is reverted and the batch goes on
+    BestEffort,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(BorshSerialize, Serialize, Deserialize, Debug, Clone)]
 pub struct Transaction {
     pub sender: String,
     pub tx_type: TransactionType,
@@ -38,4 +77,10 @@ impl Transaction {
             fee,
         }
     }
+
+    /// Hash of the canonical encoding, identifies the transaction. Fails for
+    /// transactions with NaN amounts, which are never valid.
+    pub fn hash(&self) -> Result<Hash, String> {
+        Hash::of(self)
+    }
 }
@@ -0,0 +1,9 @@
+use serde::{Deserialize, Serialize};
+
+/// Position of a transaction in the chain
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TxLocation {
+    pub height: usize,
+    /// Index of the transaction within its block
+    pub index: usize,
+}
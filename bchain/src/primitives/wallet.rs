@@ -1,17 +1,22 @@
-use serde::Serialize;
+use borsh::BorshSerialize;
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
-#[derive(Debug, Serialize)]
+#[derive(BorshSerialize, Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct PendingUnstake {
     pub amount: f64,
     pub effective_epoch: usize,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(BorshSerialize, Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Wallet {
     pub balance: f64,
     pub staked: f64,
     pub pending_unstakes: VecDeque<PendingUnstake>,
+    /// Number of contracts deployed from this account
+    pub nonce: u64,
+    /// Balance locked to pay for the contract storage of this account
+    pub storage_deposit: f64,
 }
 
 impl Wallet {
@@ -20,6 +25,8 @@ impl Wallet {
             balance,
             staked: 0.0,
             pending_unstakes: VecDeque::new(),
+            nonce: 0,
+            storage_deposit: 0.0,
         }
     }
 }
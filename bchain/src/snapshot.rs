@@ -0,0 +1,161 @@
+use crate::bchain_error::BChainError;
+use crate::clock::Clock;
+use crate::config::static_config;
+use crate::patricia_merkle_trie::{merkle_tree, state_root};
+use crate::primitives::{Block, Hash, SnapshotChunk, SnapshotManifest, StateEntry};
+use crate::Blockchain;
+use std::sync::Arc;
+
+/// State of the block at `height`, kept to be served in chunks
+#[derive(Debug)]
+pub(crate) struct Snapshot {
+    height: usize,
+    header: Block,
+    entries: Vec<StateEntry>,
+    tree: Vec<Vec<Hash>>,
+}
+
+impl Snapshot {
+    /// Snapshot of `entries`, the leaves of `tree`
+    pub fn new(
+        height: usize,
+        header: Block,
+        entries: Vec<StateEntry>,
+        tree: Vec<Vec<Hash>>,
+    ) -> Self {
+        Self {
+            height,
+            header,
+            entries,
+            tree,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn manifest(&self, recent_blocks: Vec<Block>) -> SnapshotManifest {
+        SnapshotManifest {
+            height: self.height,
+            header: self.header.clone(),
+            recent_blocks,
+            chunk_count: self
+                .entries
+                .len()
+                .div_ceil(static_config::SNAPSHOT_CHUNK_ENTRIES),
+        }
+    }
+
+    pub fn chunk(&self, index: usize) -> Option<SnapshotChunk> {
+        let start = index.checked_mul(static_config::SNAPSHOT_CHUNK_ENTRIES)?;
+        let end = (start + static_config::SNAPSHOT_CHUNK_ENTRIES).min(self.entries.len());
+        let entries = self.entries.get(start..end).filter(|e| !e.is_empty())?;
+        Some(SnapshotChunk {
+            height: self.height,
+            index,
+            entries: entries
+                .iter()
+                .zip(start..)
+                .map(|(entry, leaf)| (entry.clone(), merkle_tree::proof(&self.tree, leaf)))
+                .collect(),
+        })
+    }
+}
+
+/// Number of blocks before the one at `height` a snapshot comes with
+pub(crate) fn recent_blocks_wanted(height: usize) -> usize {
+    height.min(static_config::RECENT_BLOCK_HASHES.max(static_config::MEDIAN_TIME_BLOCKS))
+}
+
+/// Collects the chunks of a snapshot downloaded from peers, in any order and
+/// from any peer, and builds a blockchain following the chain from the
+/// snapshot's block once all of them arrived.
+#[derive(Debug)]
+pub struct SnapshotSync {
+    manifest: SnapshotManifest,
+    chunks: Vec<Option<Vec<StateEntry>>>,
+}
+
+impl SnapshotSync {
+    /// Starts syncing from `manifest`, whose header must come from a trusted
+    /// source such as the header chain
+    pub fn new(manifest: SnapshotManifest) -> Result<Self, BChainError> {
+        let blocks: Vec<&Block> = manifest
+            .recent_blocks
+            .iter()
+            .chain([&manifest.header])
+            .collect();
+        if blocks.len() != recent_blocks_wanted(manifest.height) + 1 {
+            return Err(BChainError::InvalidSnapshot(
+                "wrong number of recent blocks".to_string(),
+            ));
+        }
+        for (i, block) in blocks.iter().enumerate() {
+            if block.calculate_hash() != Ok(block.hash)
+                || i > 0 && block.previous_hash != blocks[i - 1].hash
+            {
+                return Err(BChainError::InvalidSnapshot(format!(
+                    "block at height {} does not belong to the chain",
+                    manifest.height + 1 + i - blocks.len()
+                )));
+            }
+        }
+        Ok(Self {
+            chunks: vec![None; manifest.chunk_count],
+            manifest,
+        })
+    }
+
+    /// Chunks still to be downloaded
+    pub fn missing_chunks(&self) -> Vec<usize> {
+        (0..self.chunks.len())
+            .filter(|&index| self.chunks[index].is_none())
+            .collect()
+    }
+
+    /// Verifies a chunk against the header's state root and keeps it
+    pub fn add_chunk(&mut self, chunk: SnapshotChunk) -> Result<(), BChainError> {
+        if chunk.height != self.manifest.height || chunk.index >= self.chunks.len() {
+            return Err(BChainError::InvalidSnapshot(format!(
+                "unexpected chunk {} at height {}",
+                chunk.index, chunk.height
+            )));
+        }
+        if !chunk.verify(&self.manifest.header.state_root) {
+            return Err(BChainError::InvalidSnapshot(format!(
+                "proof of chunk {} does not match the state root",
+                chunk.index
+            )));
+        }
+        self.chunks[chunk.index] = Some(chunk.entries.into_iter().map(|(e, _)| e).collect());
+        Ok(())
+    }
+
+    /// Blockchain at the snapshot height. Fails if chunks are missing or if
+    /// together they are not exactly the state the header commits to.
+    pub fn finish(self, clock: Arc<dyn Clock>) -> Result<Blockchain, BChainError> {
+        if let Some(index) = self.missing_chunks().first() {
+            return Err(BChainError::InvalidSnapshot(format!(
+                "chunk {} is missing",
+                index
+            )));
+        }
+        let entries: Vec<StateEntry> = self.chunks.into_iter().flatten().flatten().collect();
+        let (state_root, _) =
+            state_root::compute(&entries).map_err(BChainError::InvalidSnapshot)?;
+        if state_root != self.manifest.header.state_root {
+            return Err(BChainError::InvalidSnapshot(
+                "entries do not add up to the state root".to_string(),
+            ));
+        }
+        let mut blocks = self.manifest.recent_blocks;
+        blocks.push(self.manifest.header);
+        Ok(Blockchain::from_snapshot(
+            self.manifest.height,
+            blocks,
+            entries,
+            clock,
+        ))
+    }
+}
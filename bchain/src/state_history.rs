@@ -0,0 +1,127 @@
+use crate::patricia_merkle_trie::state_root;
+use crate::primitives::{Hash, StateEntry};
+use std::collections::BTreeMap;
+
+/// Which past states a node keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StateRetention {
+    /// Every state since the chain (or the snapshot it started from) began
+    Archive,
+    /// States of the last `recent_blocks` blocks and of the snapshot offered
+    /// to syncing nodes
+    Pruned { recent_blocks: usize },
+}
+
+/// Identifies a leaf of the state tree. Keys order like the leaves do.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum StateKey {
+    Wallet(String),
+    Contract(String),
+    Storage(String, String),
+    Validators,
+}
+
+impl StateKey {
+    fn of(entry: &StateEntry) -> Self {
+        match entry {
+            StateEntry::Wallet { user, .. } => Self::Wallet(user.clone()),
+            StateEntry::Contract { address, .. } => Self::Contract(address.clone()),
+            StateEntry::Storage { address, key, .. } => Self::Storage(address.clone(), key.clone()),
+            StateEntry::Validators { .. } => Self::Validators,
+        }
+    }
+}
+
+/// Past states, stored as the versions of each state entry together with the
+/// height of the block that wrote them.
+#[derive(Debug, Default)]
+pub(crate) struct StateHistory {
+    /// None marks an entry removed at that height
+    versions: BTreeMap<StateKey, BTreeMap<usize, Option<StateEntry>>>,
+    /// Leaf hashes of the last recorded state, to find what a block changed
+    latest: BTreeMap<StateKey, Hash>,
+    /// Heights from this one on are available
+    first_height: usize,
+    /// Older height still available after pruning
+    checkpoint: Option<usize>,
+}
+
+impl StateHistory {
+    /// History starting with the state after the block at `height`
+    pub fn new(height: usize, entries: &[StateEntry]) -> Result<Self, String> {
+        let mut history = Self {
+            first_height: height,
+            ..Default::default()
+        };
+        history.record(height, entries)?;
+        Ok(history)
+    }
+
+    /// Records the state after the block at `height`, keeping only what changed
+    pub fn record(&mut self, height: usize, entries: &[StateEntry]) -> Result<(), String> {
+        let mut latest = BTreeMap::new();
+        for entry in entries {
+            let key = StateKey::of(entry);
+            let hash = state_root::leaf_hash(entry)?;
+            if self.latest.get(&key) != Some(&hash) {
+                self.versions
+                    .entry(key.clone())
+                    .or_default()
+                    .insert(height, Some(entry.clone()));
+            }
+            latest.insert(key, hash);
+        }
+        for key in self.latest.keys().filter(|key| !latest.contains_key(key)) {
+            self.versions
+                .entry(key.clone())
+                .or_default()
+                .insert(height, None);
+        }
+        self.latest = latest;
+        Ok(())
+    }
+
+    pub fn is_available(&self, height: usize) -> bool {
+        height >= self.first_height || self.checkpoint == Some(height)
+    }
+
+    /// Leaves of the state after the block at `height`, in order
+    pub fn state_at(&self, height: usize) -> Vec<StateEntry> {
+        self.versions
+            .values()
+            .filter_map(|versions| versions.range(..=height).next_back())
+            .filter_map(|(_, entry)| entry.clone())
+            .collect()
+    }
+
+    /// Drops the versions not needed by heights from `keep_from` on or by
+    /// `checkpoint`
+    pub fn prune(&mut self, keep_from: usize, checkpoint: Option<usize>) {
+        if keep_from <= self.first_height {
+            return;
+        }
+        let checkpoint = checkpoint.filter(|&height| self.is_available(height));
+        for versions in self.versions.values_mut() {
+            let heights: Vec<usize> = versions.keys().copied().collect();
+            for (i, &height) in heights.iter().enumerate() {
+                // The version holds for heights up to the next one
+                let until = heights.get(i + 1).copied().unwrap_or(usize::MAX);
+                let needed =
+                    until > keep_from || checkpoint.is_some_and(|c| height <= c && c < until);
+                if !needed {
+                    versions.remove(&height);
+                }
+            }
+            // A removal with nothing before it is the same as no entry
+            while let Some(entry) = versions.first_entry() {
+                if entry.get().is_some() {
+                    break;
+                }
+                entry.remove();
+            }
+        }
+        self.versions.retain(|_, versions| !versions.is_empty());
+        self.first_height = keep_from;
+        self.checkpoint = checkpoint.filter(|&height| height < keep_from);
+    }
+}
@@ -4,7 +4,7 @@ use sha2::{Digest, Sha256};
 
 use crate::config::static_config;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TwoEpochValidators {
     current_epoch_validators: Vec<String>,
     next_epoch_validators: Vec<String>,
@@ -21,6 +21,17 @@ impl TwoEpochValidators {
         }
     }
 
+    /// Validators restored from a state snapshot
+    pub fn from_lists(
+        current_epoch_validators: Vec<String>,
+        next_epoch_validators: Vec<String>,
+    ) -> Self {
+        Self {
+            current_epoch_validators,
+            next_epoch_validators,
+        }
+    }
+
     pub fn update_validators(&mut self, stake_pool: &HashMap<String, f64>, seed: String) {
         std::mem::swap(
             &mut self.current_epoch_validators,
@@ -37,6 +48,10 @@ impl TwoEpochValidators {
         &self.current_epoch_validators
     }
 
+    pub fn get_next_epoch_validators(&self) -> &Vec<String> {
+        &self.next_epoch_validators
+    }
+
     fn get_validator_for_slots(
         stake_pool: &HashMap<String, f64>,
         seed: String,
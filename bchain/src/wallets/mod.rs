@@ -1,17 +1,63 @@
 use crate::{
-    config::config_utils,
+    config::{config_utils, static_config},
+    contracts::{evm_address, Journal},
     primitives::{PendingUnstake, Wallet},
 };
+use revm_interpreter::primitives::Address;
 use std::collections::HashMap;
 
 #[derive(Default, Debug)]
 pub struct Wallets {
     pub wallets: HashMap<String, Wallet>,
+    /// Previous wallets, for checkpoints to restore
+    pub(crate) journal: Journal<String, Wallet>,
+    /// Account of each EVM address that ever had a wallet
+    evm_accounts: HashMap<Address, String>,
 }
 
 impl Wallets {
+    /// Adds the wallet of `user` without journaling it, for state built
+    /// outside of transactions
+    pub fn insert(&mut self, user: String, wallet: Wallet) {
+        self.evm_accounts.insert(evm_address(&user), user.clone());
+        self.wallets.insert(user, wallet);
+    }
+
+    /// Name of the account behind an EVM address
+    pub fn evm_account(&self, address: &Address) -> Option<&String> {
+        self.evm_accounts.get(address)
+    }
+
+    /// Wallet of `user` to change, kept in the journal as it was
+    pub fn get_mut(&mut self, user: &str) -> Option<&mut Wallet> {
+        let wallet = self.wallets.get_mut(user)?;
+        self.journal.record(user.to_string(), Some(wallet.clone()));
+        Some(wallet)
+    }
+
+    /// Like `get_mut`, creating an empty wallet for a new `user`
+    pub fn get_or_create(&mut self, user: &str) -> &mut Wallet {
+        let previous = self.wallets.get(user).cloned();
+        if previous.is_none() {
+            self.evm_accounts
+                .insert(evm_address(user), user.to_string());
+        }
+        self.journal.record(user.to_string(), previous);
+        self.wallets
+            .entry(user.to_string())
+            .or_insert(Wallet::new(0.))
+    }
+
+    pub fn remove(&mut self, user: &str) -> Option<Wallet> {
+        let wallet = self.wallets.remove(user);
+        self.journal.record(user.to_string(), wallet.clone());
+        wallet
+    }
+
     pub fn stake(&mut self, user: &str, amount: f64, fee: f64) -> Result<(), String> {
-        let wallet = self.wallets.get_mut(user).ok_or("User not found")?;
+        check_amount(amount)?;
+        check_amount(fee)?;
+        let wallet = self.get_mut(user).ok_or("User not found")?;
         if wallet.balance < amount + fee {
             return Err("Insufficient ballance to stake".to_string());
         }
@@ -27,8 +73,10 @@ impl Wallets {
         amount: f64,
         fee: f64,
     ) -> Result<(), String> {
+        check_amount(amount)?;
+        check_amount(fee)?;
         let unstake_epoch = config_utils::get_epoch(block_height) + 2;
-        let wallet = self.wallets.get_mut(user).ok_or("User not found")?;
+        let wallet = self.get_mut(user).ok_or("User not found")?;
         if wallet.staked < amount {
             return Err("Insufficient stake to unstake".to_string());
         }
@@ -44,6 +92,16 @@ impl Wallets {
         Ok(())
     }
 
+    pub fn charge_fee(&mut self, user: &str, fee: f64) -> Result<(), String> {
+        check_amount(fee)?;
+        let wallet = self.get_mut(user).ok_or("User not found")?;
+        if wallet.balance < fee {
+            return Err(format!("Insufficient  balance for fee: {}", user));
+        }
+        wallet.balance -= fee;
+        Ok(())
+    }
+
     pub fn transfer(
         &mut self,
         sender: &str,
@@ -51,23 +109,51 @@ impl Wallets {
         amount: f64,
         fee: f64,
     ) -> Result<(), String> {
-        let sender_wallet = self.wallets.get_mut(sender).ok_or("Sender not found")?;
+        check_amount(amount)?;
+        check_amount(fee)?;
+        let sender_wallet = self.get_mut(sender).ok_or("Sender not found")?;
         if sender_wallet.balance < amount + fee {
             return Err("Insufficient balance".to_string());
         }
         sender_wallet.balance -= amount + fee;
-        let receiver_wallet = self
-            .wallets
-            .entry(receiver.to_string())
-            .or_insert(Wallet::new(0.));
-        receiver_wallet.balance += amount;
+        self.get_or_create(receiver).balance += amount;
+        Ok(())
+    }
+}
+
+impl Wallets {
+    /// Locks the deposit for `bytes` more bytes of contract storage, or releases
+    /// it back to the balance when `bytes` is negative.
+    pub fn update_storage_deposit(&mut self, contract: &str, bytes: i64) -> Result<(), String> {
+        let wallet = self.get_mut(contract).ok_or("Contract not found")?;
+        let deposit = bytes as f64 * static_config::STORAGE_DEPOSIT_PER_BYTE;
+        if wallet.balance < deposit {
+            return Err(format!(
+                "Insufficient balance for storage deposit: {}",
+                contract
+            ));
+        }
+        wallet.balance -= deposit;
+        wallet.storage_deposit += deposit;
         Ok(())
     }
 }
 
 impl Wallets {
     pub fn return_stakes(&mut self, epoch: usize) {
-        for wallet in self.wallets.values_mut() {
+        let returned: Vec<String> = self
+            .wallets
+            .iter()
+            .filter(|(_, wallet)| {
+                wallet
+                    .pending_unstakes
+                    .front()
+                    .is_some_and(|pending| pending.effective_epoch <= epoch)
+            })
+            .map(|(user, _)| user.clone())
+            .collect();
+        for user in returned {
+            let wallet = self.get_mut(&user).unwrap();
             while let Some(pending) = wallet.pending_unstakes.front() {
                 if pending.effective_epoch <= epoch {
                     wallet.balance += wallet.pending_unstakes.pop_front().unwrap().amount;
@@ -88,3 +174,12 @@ impl Wallets {
         stake_pool
     }
 }
+
+/// Amounts moved between balances must be finite and not negative
+fn check_amount(amount: f64) -> Result<(), String> {
+    if amount.is_finite() && amount >= 0.0 {
+        Ok(())
+    } else {
+        Err(format!("Invalid amount: {}", amount))
+    }
+}
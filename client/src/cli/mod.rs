@@ -1,6 +1,7 @@
 use std::io::{self, BufRead};
 
-use tokio::sync::mpsc;
+use bchain::primitives::{Transaction, TransactionType, Vm};
+use tokio::sync::{mpsc, oneshot};
 
 use crate::client_control::CtrlCommand;
 
@@ -36,7 +37,100 @@ pub fn run_cli(client_ctrl_tx: mpsc::Sender<CtrlCommand>) {
                     });
                 }
             }
+            ["query", contract_address, method, input @ ..] if input.len() <= 1 => {
+                let Some(input) = input.first().map_or(Some(Vec::new()), |hex| parse_hex(hex))
+                else {
+                    println!("Input must be hex encoded");
+                    continue;
+                };
+                let (reply_tx, reply_rx) = oneshot::channel();
+                let command = CtrlCommand::QueryContract {
+                    contract_address: contract_address.to_string(),
+                    method: method.to_string(),
+                    input,
+                    reply: reply_tx,
+                };
+                let tx = client_ctrl_tx.clone();
+                tokio::spawn(async move {
+                    if tx.send(command).await.is_err() {
+                        return;
+                    }
+                    match reply_rx.await {
+                        Ok(Ok(receipt)) => println!(
+                            "exit code {}, gas used {}, output {}{}",
+                            receipt.exit_code,
+                            receipt.gas_used,
+                            to_hex(&receipt.return_data),
+                            receipt
+                                .error
+                                .map_or(String::new(), |e| format!(", error: {}", e))
+                        ),
+                        Ok(Err(e)) => println!("Query failed: {}", e),
+                        Err(_) => println!("Query dropped"),
+                    }
+                });
+            }
+            ["deploy", sender, vm, code] => {
+                let vm = match *vm {
+                    "wasm" => Vm::Wasm,
+                    "evm" => Vm::Evm,
+                    _ => {
+                        println!("Vm must be wasm or evm");
+                        continue;
+                    }
+                };
+                let Some(code) = parse_hex(code) else {
+                    println!("Code must be hex encoded");
+                    continue;
+                };
+                let deploy = Transaction::new(
+                    sender.to_string(),
+                    TransactionType::DeployContract {
+                        code,
+                        salt: None,
+                        vm,
+                    },
+                    0.0,
+                );
+                let (reply_tx, reply_rx) = oneshot::channel();
+                let command = CtrlCommand::ProduceBlock {
+                    producer: sender.to_string(),
+                    transactions: vec![deploy],
+                    reply: reply_tx,
+                };
+                let tx = client_ctrl_tx.clone();
+                tokio::spawn(async move {
+                    if tx.send(command).await.is_err() {
+                        return;
+                    }
+                    match reply_rx.await {
+                        Ok(Ok(receipts)) => match &receipts[0].contract_address {
+                            Some(address) => println!("Deployed at {}", address),
+                            None => println!(
+                                "Deployment failed with exit code {}",
+                                receipts[0].exit_code
+                            ),
+                        },
+                        Ok(Err(e)) => println!("Deployment failed: {}", e),
+                        Err(_) => println!("Deployment dropped"),
+                    }
+                });
+            }
             x => println!("Here goes nothing {:?}", x),
         }
     }
 }
+
+fn parse_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
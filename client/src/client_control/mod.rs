@@ -1,3 +1,21 @@
+use bchain::bchain_error::BChainError;
+use bchain::primitives::{Receipt, Transaction};
+use tokio::sync::oneshot;
+
 pub enum CtrlCommand {
     InitiateConnection(String),
+    /// Block of `transactions` added to the local chain, answered on `reply`
+    /// with their receipts
+    ProduceBlock {
+        producer: String,
+        transactions: Vec<Transaction>,
+        reply: oneshot::Sender<Result<Vec<Receipt>, BChainError>>,
+    },
+    /// Read-only contract call against the local chain, answered on `reply`
+    QueryContract {
+        contract_address: String,
+        method: String,
+        input: Vec<u8>,
+        reply: oneshot::Sender<Result<Receipt, BChainError>>,
+    },
 }
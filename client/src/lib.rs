@@ -6,10 +6,12 @@ use crate::client_control::CtrlCommand;
 use crate::protocols::peer_handshake::HandshakeProtocol;
 use crate::protocols::ProtocolId;
 use crate::protocols::TwoPartyExchange;
+use bchain::message::{BlockchainFacade, Message, MessageType};
+use bchain::Blockchain;
 use network::comm::events::NodeEvent;
 use network::node::peer::Peer;
 use network::node::Node;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
@@ -17,6 +19,9 @@ use uuid::Uuid;
 pub(crate) struct Client {
     node: Arc<Node>,
     local_peer: Peer,
+    /// Chain the client maintains, blocks are produced and contracts queried
+    /// on it
+    blockchain: Arc<Mutex<Blockchain>>,
 }
 
 impl Client {
@@ -24,7 +29,11 @@ impl Client {
         let local_peer = Peer { id: Uuid::new_v4() };
         let node = Arc::new(node);
         println!("{:?} ---client--- running", local_peer);
-        Self { node, local_peer }
+        Self {
+            node,
+            local_peer,
+            blockchain: Arc::new(Mutex::new(Blockchain::new())),
+        }
     }
 }
 
@@ -46,6 +55,23 @@ pub async fn run_clinet(local_addr: String, mut ctrl_rx: mpsc::Receiver<CtrlComm
                 tokio::spawn(async move{
                     match ctrl_event {
                         CtrlCommand::InitiateConnection(addr) => connect_peer(client, addr).await,
+                        CtrlCommand::ProduceBlock { producer, transactions, reply } => {
+                            let result = with_blockchain(&client, move |blockchain| {
+                                blockchain.receive(Message {
+                                    msg_type: MessageType::ProduceBlock(producer, transactions),
+                                })?;
+                                blockchain.get_receipts(blockchain.get_height()).cloned()
+                            })
+                            .await;
+                            let _ = reply.send(result);
+                        }
+                        CtrlCommand::QueryContract { contract_address, method, input, reply } => {
+                            let result = with_blockchain(&client, move |blockchain| {
+                                blockchain.query_contract(&contract_address, &method, &input)
+                            })
+                            .await;
+                            let _ = reply.send(result);
+                        }
                     }
                 });
             }
@@ -53,6 +79,23 @@ pub async fn run_clinet(local_addr: String, mut ctrl_rx: mpsc::Receiver<CtrlComm
     }
 }
 
+/// Runs `f` on the local chain in a blocking task, as contracts it executes
+/// may take long
+async fn with_blockchain<T: Send + 'static>(
+    client: &Client,
+    f: impl FnOnce(&mut Blockchain) -> T + Send + 'static,
+) -> T {
+    let blockchain = client.blockchain.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut blockchain = blockchain
+            .lock()
+            .expect("Unrecoverable failure: blockchain mutex poisoned");
+        f(&mut blockchain)
+    })
+    .await
+    .expect("Unrecoverable failure: blockchain task panicked")
+}
+
 async fn connect_peer(client: Client, peer_addr: String) {
     if let Some(peer) = client.node.bootstrap(peer_addr.clone()).await {
         let protocol = HandshakeProtocol::from(client.local_peer);
@@ -87,3 +130,55 @@ async fn handle_network_event(client: Client, event: NodeEvent) {
         NodeEvent::NetworkMessage(_network_message) => todo!(),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bchain::primitives::{Transaction, TransactionType, Vm};
+    use tokio::sync::oneshot;
+
+    /// Creation code of an EVM contract returning 42 as a 32-byte word
+    const EVM_ANSWER: [u8; 22] = [
+        0x60, 0x0a, 0x60, 0x0c, 0x60, 0x00, 0x39, 0x60, 0x0a, 0x60, 0x00, 0xf3, // creation
+        0x60, 0x2a, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3, // runtime
+    ];
+
+    #[tokio::test]
+    async fn test_query_sees_contract_deployed_on_client_chain() {
+        let (ctrl_tx, ctrl_rx) = mpsc::channel(10);
+        tokio::spawn(run_clinet("127.0.0.1:0".to_string(), ctrl_rx));
+
+        let deploy = Transaction::new(
+            bchain::GENESIS.to_string(),
+            TransactionType::DeployContract {
+                code: EVM_ANSWER.to_vec(),
+                salt: None,
+                vm: Vm::Evm,
+            },
+            0.0,
+        );
+        let (reply, receipts) = oneshot::channel();
+        let command = CtrlCommand::ProduceBlock {
+            producer: bchain::GENESIS.to_string(),
+            transactions: vec![deploy],
+            reply,
+        };
+        ctrl_tx.send(command).await.unwrap();
+        let receipts = receipts.await.unwrap().unwrap();
+        let contract_address = receipts[0].contract_address.clone().unwrap();
+
+        let (reply, receipt) = oneshot::channel();
+        let command = CtrlCommand::QueryContract {
+            contract_address,
+            method: String::new(),
+            input: Vec::new(),
+            reply,
+        };
+        ctrl_tx.send(command).await.unwrap();
+        let receipt = receipt.await.unwrap().unwrap();
+        assert_eq!(receipt.exit_code, 0);
+        let mut answer = [0; 32];
+        answer[31] = 42;
+        assert_eq!(receipt.return_data, answer);
+    }
+}
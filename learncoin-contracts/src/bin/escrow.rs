@@ -0,0 +1,73 @@
+//! Time-locked escrow.
+//!
+//! The depositor funds the escrow with the value of the `init` call. The
+//! beneficiary can release the funds from the release height on, until then
+//! the depositor can cancel and take them back.
+#![cfg_attr(target_arch = "wasm32", no_std, no_main)]
+
+use learncoin_contracts::{entry, env, env::Address, storage};
+
+entry! {
+    /// Input: beneficiary (str), release height (u64). Can be called once.
+    fn init(input) -> i32 {
+        let (Some(beneficiary), Some(release_height)) = (input.read_str(), input.read_u64()) else {
+            return 1;
+        };
+        if storage::get_value::<u64>("release_height").is_some() {
+            return 1;
+        }
+        let stored = storage::set_value("depositor", &env::caller())
+            .and(storage::set_value("beneficiary", &Address::new(beneficiary)))
+            .and(storage::set_value("release_height", &release_height));
+        match stored {
+            Ok(()) => 0,
+            Err(_) => 1,
+        }
+    }
+
+    /// Pays the escrow out to the beneficiary, who must be the caller
+    fn release(_input) -> i32 {
+        let (Some(beneficiary), Some(release_height)) = (
+            storage::get_value::<Address>("beneficiary"),
+            storage::get_value::<u64>("release_height"),
+        ) else {
+            return 1;
+        };
+        if env::caller() != beneficiary.as_str() || env::block_height() < release_height {
+            return 1;
+        }
+        settle(&beneficiary)
+    }
+
+    /// Returns the escrow to the depositor, who must be the caller, before the release height
+    fn cancel(_input) -> i32 {
+        let (Some(depositor), Some(release_height)) = (
+            storage::get_value::<Address>("depositor"),
+            storage::get_value::<u64>("release_height"),
+        ) else {
+            return 1;
+        };
+        if env::caller() != depositor.as_str() || env::block_height() >= release_height {
+            return 1;
+        }
+        settle(&depositor)
+    }
+}
+
+fn settle(receiver: &Address) -> i32 {
+    if storage::get_value::<bool>("settled").is_some()
+        || storage::set_value("settled", &true).is_err()
+    {
+        return 1;
+    }
+    let amount = env::self_balance();
+    if amount <= 0.0 || env::transfer(receiver.as_str(), amount).is_err() {
+        return 1;
+    }
+    let _ = env::emit_event(&[env::topic("Settled")], &amount.to_le_bytes());
+    0
+}
+
+// Contracts only run as wasm, a native build just type-checks them
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {}
@@ -0,0 +1,129 @@
+//! M-of-N multisig wallet holding LearnCoin.
+//!
+//! Owners propose transfers out of the contract balance, a proposal can be
+//! executed by any owner once `threshold` owners approved it. Funds are added
+//! with the value of any call.
+#![cfg_attr(target_arch = "wasm32", no_std, no_main)]
+
+use learncoin_contracts::{
+    entry, env,
+    env::Address,
+    storage::{self, Key},
+};
+
+entry! {
+    /// Input: threshold (u32), number of owners (u32), owners (str each). Can be called once.
+    fn init(input) -> i32 {
+        let (Some(threshold), Some(count)) = (input.read_u32(), input.read_u32()) else {
+            return 1;
+        };
+        if storage::get_value::<u32>("threshold").is_some() || threshold == 0 || threshold > count {
+            return 1;
+        }
+        for _ in 0..count {
+            let Some(owner) = input.read_str() else {
+                return 1;
+            };
+            if storage::set_value(Key::new("owner").push(owner).as_str(), &true).is_err() {
+                return 1;
+            }
+        }
+        match storage::set_value("threshold", &threshold) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        }
+    }
+
+    /// Input: receiver (str), amount (f64). Approves the proposal on behalf of
+    /// the caller and returns its id (u32).
+    fn propose(input) -> i32 {
+        let (Some(to), Some(amount)) = (input.read_str(), input.read_f64()) else {
+            return 1;
+        };
+        let caller = env::caller();
+        if !is_owner(&caller) || amount <= 0.0 {
+            return 1;
+        }
+        let id = storage::get_value::<u32>("proposals").unwrap_or(0);
+        let proposal = |field| Key::new("proposal").push_u32(id).push(field);
+        let stored = storage::set_value(proposal("to").as_str(), &Address::new(to))
+            .and(storage::set_value(proposal("amount").as_str(), &amount))
+            .and(storage::set_value("proposals", &(id + 1)));
+        if stored.is_err() || approve_proposal(id, &caller) != 0 {
+            return 1;
+        }
+        env::set_return(&id.to_le_bytes());
+        0
+    }
+
+    /// Input: proposal id (u32)
+    fn approve(input) -> i32 {
+        let Some(id) = input.read_u32() else {
+            return 1;
+        };
+        approve_proposal(id, &env::caller())
+    }
+
+    /// Input: proposal id (u32). Transfers the proposed amount once enough owners approved
+    /// and the contract holds it.
+    fn execute(input) -> i32 {
+        let Some(id) = input.read_u32() else {
+            return 1;
+        };
+        if !is_owner(&env::caller()) {
+            return 1;
+        }
+        let proposal = |field| Key::new("proposal").push_u32(id).push(field);
+        let (Some(to), Some(amount)) = (
+            storage::get_value::<Address>(proposal("to").as_str()),
+            storage::get_value::<f64>(proposal("amount").as_str()),
+        ) else {
+            return 1;
+        };
+        let threshold = storage::get_value::<u32>("threshold").unwrap_or(u32::MAX);
+        let approvals = storage::get_value::<u32>(proposal("approvals").as_str()).unwrap_or(0);
+        if approvals < threshold || storage::get_value::<bool>(proposal("executed").as_str()).is_some() {
+            return 1;
+        }
+        // Proposals are not backed by funds, the balance may no longer cover one
+        if amount > env::self_balance() {
+            return 1;
+        }
+        if storage::set_value(proposal("executed").as_str(), &true).is_err() {
+            return 1;
+        }
+        if env::transfer(to.as_str(), amount).is_err() {
+            return 1;
+        }
+        let _ = env::emit_event(&[env::topic("Executed")], &id.to_le_bytes());
+        0
+    }
+}
+
+fn is_owner(address: &Address) -> bool {
+    storage::get_value::<bool>(Key::new("owner").push(address.as_str()).as_str()).unwrap_or(false)
+}
+
+fn approve_proposal(id: u32, owner: &Address) -> i32 {
+    let proposal = |field| Key::new("proposal").push_u32(id).push(field);
+    let approved = proposal("approved").push(owner.as_str());
+    if !is_owner(owner)
+        || storage::get_value::<f64>(proposal("amount").as_str()).is_none()
+        || storage::get_value::<bool>(approved.as_str()).is_some()
+    {
+        return 1;
+    }
+    let approvals = storage::get_value::<u32>(proposal("approvals").as_str()).unwrap_or(0);
+    let stored = storage::set_value(approved.as_str(), &true).and(storage::set_value(
+        proposal("approvals").as_str(),
+        &(approvals + 1),
+    ));
+    match stored {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+// Contracts only run as wasm, a native build just type-checks them
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {}
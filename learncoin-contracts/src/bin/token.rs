@@ -0,0 +1,115 @@
+//! Fungible token with ERC-20 style transfer, approve and allowance.
+//!
+//! Balances are kept in `balance:<owner>` and allowances in
+//! `allowance:<owner>:<spender>`, the storage deposit for new entries is paid
+//! from the contract balance.
+#![cfg_attr(target_arch = "wasm32", no_std, no_main)]
+
+use learncoin_contracts::{
+    entry, env,
+    storage::{self, Key},
+};
+
+entry! {
+    /// Input: total supply (u64), minted to the caller. Can be called once.
+    fn init(input) -> i32 {
+        let Some(supply) = input.read_u64() else {
+            return 1;
+        };
+        if storage::get_value::<u64>("supply").is_some() {
+            return 1;
+        }
+        if storage::set_value("supply", &supply).is_err() {
+            return 1;
+        }
+        set_balance(env::caller().as_str(), supply)
+    }
+
+    /// Input: receiver (str), amount (u64)
+    fn transfer(input) -> i32 {
+        let (Some(to), Some(amount)) = (input.read_str(), input.read_u64()) else {
+            return 1;
+        };
+        move_tokens(env::caller().as_str(), to, amount)
+    }
+
+    /// Input: spender (str), amount (u64). Replaces the previous allowance.
+    fn approve(input) -> i32 {
+        let (Some(spender), Some(amount)) = (input.read_str(), input.read_u64()) else {
+            return 1;
+        };
+        let owner = env::caller();
+        let key = Key::new("allowance").push(owner.as_str()).push(spender);
+        if storage::set_value(key.as_str(), &amount).is_err() {
+            return 1;
+        }
+        let _ = env::emit_event(&[env::topic("Approval")], &amount.to_le_bytes());
+        0
+    }
+
+    /// Input: owner (str), receiver (str), amount (u64), spends the caller's allowance
+    fn transfer_from(input) -> i32 {
+        let (Some(from), Some(to), Some(amount)) =
+            (input.read_str(), input.read_str(), input.read_u64())
+        else {
+            return 1;
+        };
+        let spender = env::caller();
+        let key = Key::new("allowance").push(from).push(spender.as_str());
+        let allowance = storage::get_value::<u64>(key.as_str()).unwrap_or(0);
+        if allowance < amount {
+            return 1;
+        }
+        if storage::set_value(key.as_str(), &(allowance - amount)).is_err() {
+            return 1;
+        }
+        move_tokens(from, to, amount)
+    }
+
+    /// Input: owner (str). Returns the balance (u64).
+    fn balance_of(input) -> i32 {
+        let Some(owner) = input.read_str() else {
+            return 1;
+        };
+        env::set_return(&balance(owner).to_le_bytes());
+        0
+    }
+
+    /// Input: owner (str), spender (str). Returns the allowance (u64).
+    fn allowance(input) -> i32 {
+        let (Some(owner), Some(spender)) = (input.read_str(), input.read_str()) else {
+            return 1;
+        };
+        let key = Key::new("allowance").push(owner).push(spender);
+        let allowance = storage::get_value::<u64>(key.as_str()).unwrap_or(0);
+        env::set_return(&allowance.to_le_bytes());
+        0
+    }
+}
+
+fn balance(owner: &str) -> u64 {
+    storage::get_value(Key::new("balance").push(owner).as_str()).unwrap_or(0)
+}
+
+fn set_balance(owner: &str, amount: u64) -> i32 {
+    match storage::set_value(Key::new("balance").push(owner).as_str(), &amount) {
+        Ok(()) => 0,
+        Err(_) => 1,
+    }
+}
+
+fn move_tokens(from: &str, to: &str, amount: u64) -> i32 {
+    let from_balance = balance(from);
+    if from_balance < amount {
+        return 1;
+    }
+    if set_balance(from, from_balance - amount) != 0 || set_balance(to, balance(to) + amount) != 0 {
+        return 1;
+    }
+    let _ = env::emit_event(&[env::topic("Transfer")], &amount.to_le_bytes());
+    0
+}
+
+// Contracts only run as wasm, a native build just type-checks them
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {}
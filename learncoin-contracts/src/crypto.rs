@@ -0,0 +1,39 @@
+//! Hashing and signature checks done natively by the host for a fixed gas price.
+
+use crate::sys;
+
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    unsafe { sys::sha256(data.as_ptr(), data.len() as u32, hash.as_mut_ptr()) };
+    hash
+}
+
+pub fn keccak256(data: &[u8]) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    unsafe { sys::keccak256(data.as_ptr(), data.len() as u32, hash.as_mut_ptr()) };
+    hash
+}
+
+pub fn ed25519_verify(public_key: &[u8; 32], signature: &[u8; 64], msg: &[u8]) -> bool {
+    unsafe {
+        sys::ed25519_verify(
+            public_key.as_ptr(),
+            signature.as_ptr(),
+            msg.as_ptr(),
+            msg.len() as u32,
+        ) == 0
+    }
+}
+
+/// Checks that `leaf` is in the state tree with the given `root`. Each proof
+/// step is a sibling hash followed by 1 if the current hash is the left child.
+pub fn verify_merkle_proof(leaf: &[u8; 32], proof: &[[u8; 33]], root: &[u8; 32]) -> bool {
+    unsafe {
+        sys::verify_merkle_proof(
+            leaf.as_ptr(),
+            proof.as_ptr() as *const u8,
+            proof.len() as u32,
+            root.as_ptr(),
+        ) == 0
+    }
+}
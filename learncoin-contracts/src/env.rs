@@ -0,0 +1,211 @@
+//! Access to the calling context and the ledger.
+
+use core::sync::atomic::{AtomicI32, Ordering};
+
+use crate::{HostError, sys};
+
+/// Longest account address the SDK can hold
+pub const MAX_ADDRESS_LEN: usize = 64;
+
+static BLOCKCHAIN_PTR_LOW: AtomicI32 = AtomicI32::new(0);
+static BLOCKCHAIN_PTR_HIGH: AtomicI32 = AtomicI32::new(0);
+
+/// Remembers the blockchain pointer passed to an entry point, called by [`crate::entry!`]
+#[doc(hidden)]
+pub fn init(blockchain_ptr_low: i32, blockchain_ptr_high: i32) {
+    BLOCKCHAIN_PTR_LOW.store(blockchain_ptr_low, Ordering::Relaxed);
+    BLOCKCHAIN_PTR_HIGH.store(blockchain_ptr_high, Ordering::Relaxed);
+}
+
+pub(crate) fn blockchain_ptr() -> (i32, i32) {
+    (
+        BLOCKCHAIN_PTR_LOW.load(Ordering::Relaxed),
+        BLOCKCHAIN_PTR_HIGH.load(Ordering::Relaxed),
+    )
+}
+
+/// Account address stored inline, as contracts have no allocator
+#[derive(Clone, Copy)]
+pub struct Address {
+    bytes: [u8; MAX_ADDRESS_LEN],
+    len: usize,
+}
+
+impl Address {
+    /// Panics if `address` is longer than [`MAX_ADDRESS_LEN`]
+    pub fn new(address: &str) -> Self {
+        let mut bytes = [0u8; MAX_ADDRESS_LEN];
+        bytes[..address.len()].copy_from_slice(address.as_bytes());
+        Address {
+            bytes,
+            len: address.len(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+}
+
+impl PartialEq<&str> for Address {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// Account that called the contract
+pub fn caller() -> Address {
+    let mut address = Address {
+        bytes: [0u8; MAX_ADDRESS_LEN],
+        len: 0,
+    };
+    let len = unsafe { sys::caller(address.bytes.as_mut_ptr()) };
+    assert!(len >= 0 && len as usize <= MAX_ADDRESS_LEN);
+    address.len = len as usize;
+    address
+}
+
+/// Address of the running contract
+pub fn self_address() -> Address {
+    let mut address = Address {
+        bytes: [0u8; MAX_ADDRESS_LEN],
+        len: 0,
+    };
+    let len = unsafe { sys::self_address(address.bytes.as_mut_ptr()) };
+    assert!(len >= 0 && len as usize <= MAX_ADDRESS_LEN);
+    address.len = len as usize;
+    address
+}
+
+/// Height of the block the call is executed in
+pub fn block_height() -> u64 {
+    let (low, high) = blockchain_ptr();
+    unsafe { sys::block_height(low, high) as u64 }
+}
+
+/// Timestamp of the block the call is executed in, in seconds
+pub fn block_timestamp() -> i64 {
+    let (low, high) = blockchain_ptr();
+    unsafe { sys::block_timestamp(low, high) }
+}
+
+pub fn block_epoch() -> u64 {
+    let (low, high) = blockchain_ptr();
+    unsafe { sys::block_epoch(low, high) as u64 }
+}
+
+/// Validator producing the block the call is executed in
+pub fn block_validator() -> Address {
+    let (low, high) = blockchain_ptr();
+    let mut address = Address {
+        bytes: [0u8; MAX_ADDRESS_LEN],
+        len: 0,
+    };
+    let len = unsafe { sys::block_validator(low, high, address.bytes.as_mut_ptr()) };
+    assert!(len >= 0 && len as usize <= MAX_ADDRESS_LEN);
+    address.len = len as usize;
+    address
+}
+
+/// Hash of a recent block below the current one, `None` for older blocks
+pub fn block_hash(height: u64) -> Option<[u8; 32]> {
+    let (low, high) = blockchain_ptr();
+    let mut hash = [0u8; 32];
+    match unsafe { sys::block_hash(low, high, height as i64, hash.as_mut_ptr()) } {
+        0 => Some(hash),
+        _ => None,
+    }
+}
+
+/// Copies the call input into `buffer`, panics if it does not fit
+pub fn read_input(buffer: &mut [u8]) -> &[u8] {
+    let len = unsafe { sys::input_len() } as usize;
+    assert!(len <= buffer.len());
+    unsafe { sys::read_input(buffer.as_mut_ptr()) };
+    &buffer[..len]
+}
+
+/// Sets the data returned in the receipt of the call
+pub fn set_return(data: &[u8]) {
+    unsafe { sys::set_return(data.as_ptr(), data.len() as u32) };
+}
+
+pub fn balance(user: &str) -> f64 {
+    let (low, high) = blockchain_ptr();
+    unsafe { sys::get_balance(low, high, user.as_ptr(), user.len() as u32) }
+}
+
+/// Balance of the contract itself
+pub fn self_balance() -> f64 {
+    let (low, high) = blockchain_ptr();
+    unsafe { sys::self_balance(low, high) }
+}
+
+/// Pays `amount` out of the contract's own balance
+pub fn transfer(to: &str, amount: f64) -> Result<(), HostError> {
+    let (low, high) = blockchain_ptr();
+    HostError::check(unsafe { sys::transfer(low, high, to.as_ptr(), to.len() as u32, amount) })
+}
+
+/// Stakes `amount` out of the contract's own balance
+pub fn stake(amount: f64) -> Result<(), HostError> {
+    let (low, high) = blockchain_ptr();
+    HostError::check(unsafe { sys::stake(low, high, amount) })
+}
+
+/// Unstakes `amount` of the contract's own stake
+pub fn unstake(amount: f64) -> Result<(), HostError> {
+    let (low, high) = blockchain_ptr();
+    HostError::check(unsafe { sys::unstake(low, high, amount) })
+}
+
+/// Calls `method` of another contract, passing `value` along. Fails with the
+/// exit code of the callee, whose output can be read with [`read_call_output`].
+pub fn call(address: &str, method: &str, input: &[u8], value: f64) -> Result<(), HostError> {
+    let (low, high) = blockchain_ptr();
+    HostError::check(unsafe {
+        sys::call_contract(
+            low,
+            high,
+            address.as_ptr(),
+            address.len() as u32,
+            method.as_ptr(),
+            method.len() as u32,
+            input.as_ptr(),
+            input.len() as u32,
+            value,
+        )
+    })
+}
+
+/// Copies the return data of the last [`call`] into `buffer`, panics if it does not fit
+pub fn read_call_output(buffer: &mut [u8]) -> &[u8] {
+    let len = unsafe { sys::call_output_len() } as usize;
+    assert!(len <= buffer.len());
+    unsafe { sys::read_call_output(buffer.as_mut_ptr()) };
+    &buffer[..len]
+}
+
+/// Emits an event with up to 4 topics, kept only if the call succeeds
+pub fn emit_event(topics: &[[u8; 32]], data: &[u8]) -> Result<(), HostError> {
+    HostError::check(unsafe {
+        sys::emit_event(
+            topics.as_ptr() as *const u8,
+            topics.len() as u32,
+            data.as_ptr(),
+            data.len() as u32,
+        )
+    })
+}
+
+/// Event topic made of `name` padded with zeros, panics if it is longer than 32 bytes
+pub fn topic(name: &str) -> [u8; 32] {
+    let mut topic = [0u8; 32];
+    topic[..name.len()].copy_from_slice(name.as_bytes());
+    topic
+}
+
+/// Prints a debug message on the node
+pub fn log(msg: &str, value: u32) {
+    unsafe { sys::debug(msg.as_ptr(), msg.len() as u32, value) };
+}
@@ -0,0 +1,101 @@
+//! Decoding of call input.
+//!
+//! Integers and floats are little-endian, byte strings and strings are
+//! prefixed with their length as a little-endian `u32`.
+
+/// Largest input an entry point accepts
+pub const MAX_INPUT_LEN: usize = 1024;
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+macro_rules! read_number {
+    ($($name:ident => $ty:ty),*) => {
+        $(
+            pub fn $name(&mut self) -> Option<$ty> {
+                let bytes = self.take(size_of::<$ty>())?;
+                Some(<$ty>::from_le_bytes(bytes.try_into().ok()?))
+            }
+        )*
+    };
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    read_number!(
+        read_u8 => u8,
+        read_u32 => u32,
+        read_u64 => u64,
+        read_i32 => i32,
+        read_i64 => i64,
+        read_f64 => f64
+    );
+
+    pub fn read_bool(&mut self) -> Option<bool> {
+        self.read_u8().map(|b| b != 0)
+    }
+
+    pub fn read_bytes(&mut self) -> Option<&'a [u8]> {
+        let start = self.pos;
+        let len = self.read_u32()? as usize;
+        let bytes = self.take(len);
+        if bytes.is_none() {
+            self.pos = start;
+        }
+        bytes
+    }
+
+    pub fn read_str(&mut self) -> Option<&'a str> {
+        core::str::from_utf8(self.read_bytes()?).ok()
+    }
+
+    /// Unread part of the input
+    pub fn remaining(&self) -> &'a [u8] {
+        &self.bytes[self.pos..]
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(len)?;
+        let bytes = self.bytes.get(self.pos..end)?;
+        self.pos = end;
+        Some(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_encoded_values() {
+        let mut input = Vec::new();
+        input.extend_from_slice(&7u32.to_le_bytes());
+        input.extend_from_slice(&3u32.to_le_bytes());
+        input.extend_from_slice(b"Bob");
+        input.extend_from_slice(&1.5f64.to_le_bytes());
+        input.push(1);
+
+        let mut reader = Reader::new(&input);
+        assert_eq!(reader.read_u32(), Some(7));
+        assert_eq!(reader.read_str(), Some("Bob"));
+        assert_eq!(reader.read_f64(), Some(1.5));
+        assert_eq!(reader.read_bool(), Some(true));
+        assert_eq!(reader.read_u8(), None);
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let mut input = Vec::new();
+        input.extend_from_slice(&10u32.to_le_bytes());
+        input.extend_from_slice(b"short");
+
+        let mut reader = Reader::new(&input);
+        assert_eq!(reader.read_bytes(), None);
+        assert_eq!(reader.remaining().len(), 9);
+    }
+}
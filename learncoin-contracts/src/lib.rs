@@ -1,3 +1,62 @@
-pub fn placeholder() {
-    // This crate is only used for WebAssembly binaries
+//! SDK for LearnCoin contracts.
+//!
+//! Wraps the host functions provided by `bchain` in safe functions and hides
+//! the blockchain pointer every host call expects. Contracts declare their
+//! entry points with [`entry!`] and are built for `wasm32-unknown-unknown`.
+#![cfg_attr(not(test), no_std)]
+
+pub mod crypto;
+pub mod env;
+pub mod input;
+pub mod storage;
+mod sys;
+
+/// Error code returned by a host function
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HostError(pub i32);
+
+impl HostError {
+    pub(crate) fn check(code: i32) -> Result<(), HostError> {
+        match code {
+            0 => Ok(()),
+            code => Err(HostError(code)),
+        }
+    }
+}
+
+/// Declares contract entry points. Each function is exported under its own
+/// name, so it can be called as a `method` of `CallContract`, and receives the
+/// call input as an [`input::Reader`]. The returned value is the exit code,
+/// anything but 0 reverts the call.
+///
+/// ```ignore
+/// entry! {
+///     fn increment(input) -> i32 {
+///         let by = input.read_u32().unwrap_or(1);
+///         0
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! entry {
+    ($($(#[$attr:meta])* fn $name:ident($input:ident) -> i32 $body:block)*) => {
+        $(
+            $(#[$attr])*
+            #[cfg_attr(target_arch = "wasm32", unsafe(no_mangle))]
+            #[allow(dead_code, unused_mut, unused_variables)]
+            pub extern "C" fn $name(blockchain_ptr_low: i32, blockchain_ptr_high: i32) -> i32 {
+                $crate::env::init(blockchain_ptr_low, blockchain_ptr_high);
+                let mut buffer = [0u8; $crate::input::MAX_INPUT_LEN];
+                let mut $input = $crate::input::Reader::new($crate::env::read_input(&mut buffer));
+                $body
+            }
+        )*
+    };
+}
+
+/// Panics trap the wasm instance, which makes the host revert the call
+#[cfg(target_arch = "wasm32")]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    core::arch::wasm32::unreachable()
 }
@@ -0,0 +1,185 @@
+//! Contract key-value storage.
+
+use crate::{
+    HostError,
+    env::{Address, MAX_ADDRESS_LEN, blockchain_ptr},
+    sys,
+};
+
+/// Largest value [`get_value`] can load
+pub const MAX_VALUE_LEN: usize = 64;
+
+/// Longest key a [`Key`] can hold
+pub const MAX_KEY_LEN: usize = 160;
+
+/// Storage key made of `:` separated parts, e.g. `balance:Alice`
+pub struct Key {
+    bytes: [u8; MAX_KEY_LEN],
+    len: usize,
+}
+
+impl Key {
+    pub fn new(prefix: &str) -> Self {
+        let mut key = Key {
+            bytes: [0u8; MAX_KEY_LEN],
+            len: 0,
+        };
+        key.append(prefix.as_bytes());
+        key
+    }
+
+    /// Appends `:part`, panics if the key gets longer than [`MAX_KEY_LEN`]
+    pub fn push(mut self, part: &str) -> Self {
+        self.append(b":");
+        self.append(part.as_bytes());
+        self
+    }
+
+    /// Appends `:n` in decimal
+    pub fn push_u32(mut self, mut n: u32) -> Self {
+        let mut digits = [0u8; 10];
+        let mut start = digits.len();
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (n % 10) as u8;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        self.append(b":");
+        self.append(&digits[start..]);
+        self
+    }
+
+    pub fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or_default()
+    }
+
+    fn append(&mut self, bytes: &[u8]) {
+        self.bytes[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+}
+
+/// Loads the value under `key` into `buffer`, which must be large enough to
+/// hold it. Returns `None` if the key is not set.
+pub fn get<'a>(key: &str, buffer: &'a mut [u8]) -> Option<&'a [u8]> {
+    let (low, high) = blockchain_ptr();
+    let len = unsafe {
+        sys::load(
+            low,
+            high,
+            key.as_ptr(),
+            key.len() as u32,
+            buffer.as_mut_ptr(),
+        )
+    };
+    if len < 0 {
+        return None;
+    }
+    Some(&buffer[..len as usize])
+}
+
+/// Stores `value` under `key`, locking the storage deposit for any growth
+pub fn set(key: &str, value: &[u8]) -> Result<(), HostError> {
+    let (low, high) = blockchain_ptr();
+    HostError::check(unsafe {
+        sys::store(
+            low,
+            high,
+            key.as_ptr(),
+            key.len() as u32,
+            value.as_ptr(),
+            value.len() as u32,
+        )
+    })
+}
+
+/// Removes `key`, releasing its storage deposit
+pub fn remove(key: &str) -> Result<(), HostError> {
+    let (low, high) = blockchain_ptr();
+    HostError::check(unsafe { sys::remove(low, high, key.as_ptr(), key.len() as u32) })
+}
+
+/// Value with a fixed little-endian encoding in storage
+pub trait Storable: Sized {
+    fn encode(&self, buffer: &mut [u8; MAX_VALUE_LEN]) -> usize;
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! storable_number {
+    ($($ty:ty),*) => {
+        $(
+            impl Storable for $ty {
+                fn encode(&self, buffer: &mut [u8; MAX_VALUE_LEN]) -> usize {
+                    let bytes = self.to_le_bytes();
+                    buffer[..bytes.len()].copy_from_slice(&bytes);
+                    bytes.len()
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    Some(<$ty>::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+storable_number!(u8, u32, u64, i32, i64, f64);
+
+impl Storable for bool {
+    fn encode(&self, buffer: &mut [u8; MAX_VALUE_LEN]) -> usize {
+        buffer[0] = *self as u8;
+        1
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [b] => Some(*b == 1),
+            _ => None,
+        }
+    }
+}
+
+impl Storable for Address {
+    fn encode(&self, buffer: &mut [u8; MAX_VALUE_LEN]) -> usize {
+        let bytes = self.as_str().as_bytes();
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        bytes.len()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > MAX_ADDRESS_LEN {
+            return None;
+        }
+        Some(Address::new(core::str::from_utf8(bytes).ok()?))
+    }
+}
+
+/// Loads a typed value, `None` if the key is not set or holds something else
+pub fn get_value<T: Storable>(key: &str) -> Option<T> {
+    let mut buffer = [0u8; MAX_VALUE_LEN];
+    T::decode(get(key, &mut buffer)?)
+}
+
+pub fn set_value<T: Storable>(key: &str, value: &T) -> Result<(), HostError> {
+    let mut buffer = [0u8; MAX_VALUE_LEN];
+    let len = value.encode(&mut buffer);
+    set(key, &buffer[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_key_parts() {
+        let key = Key::new("proposal")
+            .push_u32(0)
+            .push("approved")
+            .push("Alice");
+        assert_eq!(key.as_str(), "proposal:0:approved:Alice");
+        assert_eq!(Key::new("n").push_u32(4096).as_str(), "n:4096");
+    }
+}
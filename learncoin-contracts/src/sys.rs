@@ -0,0 +1,94 @@
+//! Raw imports of the host functions defined in `bchain::contracts::host`.
+
+unsafe extern "C" {
+    pub fn get_balance(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        user_ptr: *const u8,
+        user_len: u32,
+    ) -> f64;
+    pub fn self_balance(blockchain_ptr_low: i32, blockchain_ptr_high: i32) -> f64;
+    pub fn transfer(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        to_ptr: *const u8,
+        to_len: u32,
+        amount: f64,
+    ) -> i32;
+    pub fn store(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        key_ptr: *const u8,
+        key_len: u32,
+        value_ptr: *const u8,
+        value_len: u32,
+    ) -> i32;
+    pub fn load(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        key_ptr: *const u8,
+        key_len: u32,
+        value_ptr: *mut u8,
+    ) -> i32;
+    pub fn remove(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        key_ptr: *const u8,
+        key_len: u32,
+    ) -> i32;
+    pub fn stake(blockchain_ptr_low: i32, blockchain_ptr_high: i32, amount: f64) -> i32;
+    pub fn unstake(blockchain_ptr_low: i32, blockchain_ptr_high: i32, amount: f64) -> i32;
+    pub fn self_address(value_ptr: *mut u8) -> i32;
+    pub fn block_height(blockchain_ptr_low: i32, blockchain_ptr_high: i32) -> i64;
+    pub fn block_timestamp(blockchain_ptr_low: i32, blockchain_ptr_high: i32) -> i64;
+    pub fn block_epoch(blockchain_ptr_low: i32, blockchain_ptr_high: i32) -> i64;
+    pub fn block_validator(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        value_ptr: *mut u8,
+    ) -> i32;
+    pub fn block_hash(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        height: i64,
+        value_ptr: *mut u8,
+    ) -> i32;
+    pub fn caller(value_ptr: *mut u8) -> i32;
+    pub fn input_len() -> i32;
+    pub fn read_input(value_ptr: *mut u8) -> i32;
+    pub fn set_return(value_ptr: *const u8, value_len: u32) -> i32;
+    pub fn call_contract(
+        blockchain_ptr_low: i32,
+        blockchain_ptr_high: i32,
+        address_ptr: *const u8,
+        address_len: u32,
+        method_ptr: *const u8,
+        method_len: u32,
+        input_ptr: *const u8,
+        input_len: u32,
+        value: f64,
+    ) -> i32;
+    pub fn call_output_len() -> i32;
+    pub fn read_call_output(value_ptr: *mut u8) -> i32;
+    pub fn emit_event(
+        topics_ptr: *const u8,
+        topics_count: u32,
+        data_ptr: *const u8,
+        data_len: u32,
+    ) -> i32;
+    pub fn sha256(data_ptr: *const u8, data_len: u32, out_ptr: *mut u8) -> i32;
+    pub fn keccak256(data_ptr: *const u8, data_len: u32, out_ptr: *mut u8) -> i32;
+    pub fn ed25519_verify(
+        public_key_ptr: *const u8,
+        signature_ptr: *const u8,
+        msg_ptr: *const u8,
+        msg_len: u32,
+    ) -> i32;
+    pub fn verify_merkle_proof(
+        leaf_ptr: *const u8,
+        proof_ptr: *const u8,
+        proof_len: u32,
+        root_ptr: *const u8,
+    ) -> i32;
+    pub fn debug(msg_ptr: *const u8, msg_len: u32, value: u32);
+}
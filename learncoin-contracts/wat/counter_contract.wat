@@ -0,0 +1,33 @@
+;; Hand-written equivalent of src/bin/counter.rs, used by tests when the
+;; wasm32-unknown-unknown target is not installed.
+(module
+  (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "load" (func $load (param i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "stake" (func $stake (param i32 i32 f64) (result i32)))
+  (import "env" "unstake" (func $unstake (param i32 i32 f64) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 16) "counter")
+  ;; 64: counter value
+
+  (func (export "execute") (param $lo i32) (param $hi i32) (result i32)
+    (local $counter i32)
+
+    (if (i32.ge_s (call $load (local.get $lo) (local.get $hi) (i32.const 16) (i32.const 7) (i32.const 64))
+                  (i32.const 0))
+      (then (local.set $counter (i32.load (i32.const 64)))))
+    (local.set $counter (i32.add (local.get $counter) (i32.const 1)))
+    (i32.store (i32.const 64) (local.get $counter))
+    (if (call $store (local.get $lo) (local.get $hi) (i32.const 16) (i32.const 7) (i32.const 64) (i32.const 4))
+      (then (return (i32.const 1))))
+
+    (if (i32.eq (local.get $counter) (i32.const 3))
+      (then
+        (if (call $stake (local.get $lo) (local.get $hi) (f64.const 10))
+          (then (return (i32.const 1))))))
+    (if (i32.gt_s (local.get $counter) (i32.const 4))
+      (then
+        (if (call $unstake (local.get $lo) (local.get $hi) (f64.const 5))
+          (then (return (i32.const 1))))))
+
+    (i32.const 0))
+)
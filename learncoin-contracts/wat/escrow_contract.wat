@@ -0,0 +1,126 @@
+;; Hand-written equivalent of src/bin/escrow.rs, used by tests when the
+;; wasm32-unknown-unknown target is not installed. Malformed input traps.
+(module
+  (import "env" "self_balance" (func $self_balance (param i32 i32) (result f64)))
+  (import "env" "transfer" (func $transfer (param i32 i32 i32 i32 f64) (result i32)))
+  (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "load" (func $load (param i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "caller" (func $caller (param i32) (result i32)))
+  (import "env" "block_height" (func $block_height (param i32 i32) (result i64)))
+  (import "env" "input_len" (func $input_len (result i32)))
+  (import "env" "read_input" (func $read_input (param i32) (result i32)))
+  (import "env" "emit_event" (func $emit_event (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "depositor")
+  (data (i32.const 16) "beneficiary")
+  (data (i32.const 32) "release_height")
+  (data (i32.const 48) "settled")
+  (data (i32.const 64) "Settled")
+  ;; 256: input, 1600: value, 1680: caller, 1760: self address, 1840: stored address
+  (global $lo (mut i32) (i32.const 0))
+  (global $hi (mut i32) (i32.const 0))
+  (global $pos (mut i32) (i32.const 0))
+  (global $len (mut i32) (i32.const 0))
+
+  (func $enter (param $lo i32) (param $hi i32)
+    (global.set $lo (local.get $lo))
+    (global.set $hi (local.get $hi))
+    (global.set $pos (i32.const 0))
+    (global.set $len (call $input_len))
+    (if (i32.gt_u (global.get $len) (i32.const 1024)) (then unreachable))
+    (drop (call $read_input (i32.const 256))))
+
+  (func $take (param $n i32) (result i32)
+    (local $ptr i32)
+    (if (i32.gt_u (local.get $n) (i32.sub (global.get $len) (global.get $pos))) (then unreachable))
+    (local.set $ptr (i32.add (i32.const 256) (global.get $pos)))
+    (global.set $pos (i32.add (global.get $pos) (local.get $n)))
+    (local.get $ptr))
+
+  (func $read_u32 (result i32) (i32.load (call $take (i32.const 4))))
+  (func $read_u64 (result i64) (i64.load (call $take (i32.const 8))))
+  ;; Leaves the pointer and the length of the string
+  (func $read_str (result i32 i32)
+    (local $n i32)
+    (local.set $n (call $read_u32))
+    (call $take (local.get $n))
+    (local.get $n))
+
+  (func $put (param $key i32) (param $key_len i32) (param $value i32) (param $value_len i32) (result i32)
+    (call $store (global.get $lo) (global.get $hi)
+      (local.get $key) (local.get $key_len) (local.get $value) (local.get $value_len)))
+
+  (func $get (param $key i32) (param $key_len i32) (param $value i32) (result i32)
+    (call $load (global.get $lo) (global.get $hi) (local.get $key) (local.get $key_len) (local.get $value)))
+
+  (func $is_caller (param $address i32) (param $address_len i32) (result i32)
+    (local $i i32)
+    (if (i32.ne (call $caller (i32.const 1680)) (local.get $address_len)) (then (return (i32.const 0))))
+    (block $done
+      (loop $bytes
+        (br_if $done (i32.eq (local.get $i) (local.get $address_len)))
+        (if (i32.ne (i32.load8_u (i32.add (i32.const 1680) (local.get $i)))
+                    (i32.load8_u (i32.add (local.get $address) (local.get $i))))
+          (then (return (i32.const 0))))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $bytes)))
+    (i32.const 1))
+
+  (func $settle (param $receiver i32) (param $receiver_len i32) (result i32)
+    (local $amount f64)
+    (if (i32.ge_s (call $get (i32.const 48) (i32.const 7) (i32.const 1600)) (i32.const 0))
+      (then (return (i32.const 1))))
+    (i32.store8 (i32.const 1600) (i32.const 1))
+    (if (call $put (i32.const 48) (i32.const 7) (i32.const 1600) (i32.const 1))
+      (then (return (i32.const 1))))
+    (local.set $amount (call $self_balance (global.get $lo) (global.get $hi)))
+    (if (f64.le (local.get $amount) (f64.const 0)) (then (return (i32.const 1))))
+    (if (call $transfer (global.get $lo) (global.get $hi)
+          (local.get $receiver) (local.get $receiver_len) (local.get $amount))
+      (then (return (i32.const 1))))
+    (f64.store (i32.const 1600) (local.get $amount))
+    (drop (call $emit_event (i32.const 64) (i32.const 1) (i32.const 1600) (i32.const 8)))
+    (i32.const 0))
+
+  (func (export "init") (param $lo i32) (param $hi i32) (result i32)
+    (local $beneficiary i32) (local $beneficiary_len i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $read_str)
+    (local.set $beneficiary_len)
+    (local.set $beneficiary)
+    (i64.store (i32.const 1600) (call $read_u64))
+    (if (i32.gt_u (local.get $beneficiary_len) (i32.const 64)) (then unreachable))
+    (if (i32.ge_s (call $get (i32.const 32) (i32.const 14) (i32.const 1664)) (i32.const 0))
+      (then (return (i32.const 1))))
+    (if (call $put (i32.const 0) (i32.const 9) (i32.const 1680) (call $caller (i32.const 1680)))
+      (then (return (i32.const 1))))
+    (if (call $put (i32.const 16) (i32.const 11) (local.get $beneficiary) (local.get $beneficiary_len))
+      (then (return (i32.const 1))))
+    (if (call $put (i32.const 32) (i32.const 14) (i32.const 1600) (i32.const 8))
+      (then (return (i32.const 1))))
+    (i32.const 0))
+
+  (func (export "release") (param $lo i32) (param $hi i32) (result i32)
+    (local $beneficiary_len i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (local.set $beneficiary_len (call $get (i32.const 16) (i32.const 11) (i32.const 1840)))
+    (if (i32.lt_s (local.get $beneficiary_len) (i32.const 0)) (then (return (i32.const 1))))
+    (if (i32.lt_s (call $get (i32.const 32) (i32.const 14) (i32.const 1600)) (i32.const 0))
+      (then (return (i32.const 1))))
+    (if (i32.or (i32.eqz (call $is_caller (i32.const 1840) (local.get $beneficiary_len)))
+                (i64.lt_u (call $block_height (local.get $lo) (local.get $hi)) (i64.load (i32.const 1600))))
+      (then (return (i32.const 1))))
+    (call $settle (i32.const 1840) (local.get $beneficiary_len)))
+
+  (func (export "cancel") (param $lo i32) (param $hi i32) (result i32)
+    (local $depositor_len i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (local.set $depositor_len (call $get (i32.const 0) (i32.const 9) (i32.const 1840)))
+    (if (i32.lt_s (local.get $depositor_len) (i32.const 0)) (then (return (i32.const 1))))
+    (if (i32.lt_s (call $get (i32.const 32) (i32.const 14) (i32.const 1600)) (i32.const 0))
+      (then (return (i32.const 1))))
+    (if (i32.or (i32.eqz (call $is_caller (i32.const 1840) (local.get $depositor_len)))
+                (i64.ge_u (call $block_height (local.get $lo) (local.get $hi)) (i64.load (i32.const 1600))))
+      (then (return (i32.const 1))))
+    (call $settle (i32.const 1840) (local.get $depositor_len)))
+)
@@ -0,0 +1,205 @@
+;; Hand-written equivalent of src/bin/multisig.rs, used by tests when the
+;; wasm32-unknown-unknown target is not installed. Malformed input traps.
+(module
+  (import "env" "self_balance" (func $self_balance (param i32 i32) (result f64)))
+  (import "env" "transfer" (func $transfer (param i32 i32 i32 i32 f64) (result i32)))
+  (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "load" (func $load (param i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "caller" (func $caller (param i32) (result i32)))
+  (import "env" "input_len" (func $input_len (result i32)))
+  (import "env" "read_input" (func $read_input (param i32) (result i32)))
+  (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+  (import "env" "emit_event" (func $emit_event (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "owner:")
+  (data (i32.const 16) "threshold")
+  (data (i32.const 32) "proposals")
+  (data (i32.const 48) "proposal:")
+  (data (i32.const 64) ":to")
+  (data (i32.const 80) ":amount")
+  (data (i32.const 96) ":approvals")
+  (data (i32.const 112) ":executed")
+  (data (i32.const 128) ":approved:")
+  (data (i32.const 160) "Executed")
+  ;; 256: input, 1280: key, 1600: value, 1680: caller, 1760: self address,
+  ;; 1840: proposal receiver, 1920: decimal digits
+  (global $lo (mut i32) (i32.const 0))
+  (global $hi (mut i32) (i32.const 0))
+  (global $pos (mut i32) (i32.const 0))
+  (global $len (mut i32) (i32.const 0))
+
+  (func $enter (param $lo i32) (param $hi i32)
+    (global.set $lo (local.get $lo))
+    (global.set $hi (local.get $hi))
+    (global.set $pos (i32.const 0))
+    (global.set $len (call $input_len))
+    (if (i32.gt_u (global.get $len) (i32.const 1024)) (then unreachable))
+    (drop (call $read_input (i32.const 256))))
+
+  (func $take (param $n i32) (result i32)
+    (local $ptr i32)
+    (if (i32.gt_u (local.get $n) (i32.sub (global.get $len) (global.get $pos))) (then unreachable))
+    (local.set $ptr (i32.add (i32.const 256) (global.get $pos)))
+    (global.set $pos (i32.add (global.get $pos) (local.get $n)))
+    (local.get $ptr))
+
+  (func $read_u32 (result i32) (i32.load (call $take (i32.const 4))))
+  (func $read_f64 (result f64) (f64.load (call $take (i32.const 8))))
+  ;; Leaves the pointer and the length of the string
+  (func $read_str (result i32 i32)
+    (local $n i32)
+    (local.set $n (call $read_u32))
+    (call $take (local.get $n))
+    (local.get $n))
+
+  (func $append (param $len i32) (param $src i32) (param $src_len i32) (result i32)
+    (if (i32.gt_u (i32.add (local.get $len) (local.get $src_len)) (i32.const 160)) (then unreachable))
+    (memory.copy (i32.add (i32.const 1280) (local.get $len)) (local.get $src) (local.get $src_len))
+    (i32.add (local.get $len) (local.get $src_len)))
+
+  (func $append_u32 (param $len i32) (param $n i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (i32.const 1930))
+    (loop $digits
+      (local.set $ptr (i32.sub (local.get $ptr) (i32.const 1)))
+      (i32.store8 (local.get $ptr) (i32.add (i32.const 48) (i32.rem_u (local.get $n) (i32.const 10))))
+      (local.set $n (i32.div_u (local.get $n) (i32.const 10)))
+      (br_if $digits (local.get $n)))
+    (call $append (local.get $len) (local.get $ptr) (i32.sub (i32.const 1930) (local.get $ptr))))
+
+  ;; Builds `proposal:<id><field>`, fields start with `:`
+  (func $proposal_key (param $id i32) (param $field i32) (param $field_len i32) (result i32)
+    (call $append
+      (call $append_u32 (call $append (i32.const 0) (i32.const 48) (i32.const 9)) (local.get $id))
+      (local.get $field) (local.get $field_len)))
+
+  (func $has (param $key_len i32) (result i32)
+    (i32.ge_s (call $load (global.get $lo) (global.get $hi) (i32.const 1280) (local.get $key_len) (i32.const 1600))
+              (i32.const 0)))
+
+  ;; Value under the key built at 1280, 0 if not set
+  (func $get_u32 (param $key_len i32) (result i32)
+    (i32.store (i32.const 1600) (i32.const 0))
+    (drop (call $load (global.get $lo) (global.get $hi) (i32.const 1280) (local.get $key_len) (i32.const 1600)))
+    (i32.load (i32.const 1600)))
+
+  (func $set_u32 (param $key_len i32) (param $value i32) (result i32)
+    (i32.store (i32.const 1600) (local.get $value))
+    (call $store (global.get $lo) (global.get $hi) (i32.const 1280) (local.get $key_len) (i32.const 1600) (i32.const 4)))
+
+  (func $set_true (param $key_len i32) (result i32)
+    (i32.store8 (i32.const 1600) (i32.const 1))
+    (call $store (global.get $lo) (global.get $hi) (i32.const 1280) (local.get $key_len) (i32.const 1600) (i32.const 1)))
+
+  (func $is_owner (param $owner i32) (param $owner_len i32) (result i32)
+    (i32.store8 (i32.const 1600) (i32.const 0))
+    (drop (call $load (global.get $lo) (global.get $hi) (i32.const 1280)
+      (call $append (call $append (i32.const 0) (i32.const 0) (i32.const 6)) (local.get $owner) (local.get $owner_len))
+      (i32.const 1600)))
+    (i32.eq (i32.load8_u (i32.const 1600)) (i32.const 1)))
+
+  (func $approve_proposal (param $id i32) (param $owner i32) (param $owner_len i32) (result i32)
+    (local $approvals i32)
+    (if (i32.eqz (call $is_owner (local.get $owner) (local.get $owner_len))) (then (return (i32.const 1))))
+    (if (i32.eqz (call $has (call $proposal_key (local.get $id) (i32.const 80) (i32.const 7))))
+      (then (return (i32.const 1))))
+    (if (call $has (call $append (call $proposal_key (local.get $id) (i32.const 128) (i32.const 10))
+          (local.get $owner) (local.get $owner_len)))
+      (then (return (i32.const 1))))
+    (local.set $approvals (call $get_u32 (call $proposal_key (local.get $id) (i32.const 96) (i32.const 10))))
+    (if (call $set_true (call $append (call $proposal_key (local.get $id) (i32.const 128) (i32.const 10))
+          (local.get $owner) (local.get $owner_len)))
+      (then (return (i32.const 1))))
+    (call $set_u32 (call $proposal_key (local.get $id) (i32.const 96) (i32.const 10))
+      (i32.add (local.get $approvals) (i32.const 1))))
+
+  (func (export "init") (param $lo i32) (param $hi i32) (result i32)
+    (local $threshold i32) (local $count i32) (local $owner i32) (local $owner_len i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (local.set $threshold (call $read_u32))
+    (local.set $count (call $read_u32))
+    (if (i32.or (call $has (call $append (i32.const 0) (i32.const 16) (i32.const 9)))
+          (i32.or (i32.eqz (local.get $threshold))
+                  (i32.gt_u (local.get $threshold) (local.get $count))))
+      (then (return (i32.const 1))))
+    (block $done
+      (loop $owners
+        (br_if $done (i32.eqz (local.get $count)))
+        (call $read_str)
+        (local.set $owner_len)
+        (local.set $owner)
+        (if (call $set_true (call $append (call $append (i32.const 0) (i32.const 0) (i32.const 6))
+              (local.get $owner) (local.get $owner_len)))
+          (then (return (i32.const 1))))
+        (local.set $count (i32.sub (local.get $count) (i32.const 1)))
+        (br $owners)))
+    (call $set_u32 (call $append (i32.const 0) (i32.const 16) (i32.const 9)) (local.get $threshold)))
+
+  (func (export "propose") (param $lo i32) (param $hi i32) (result i32)
+    (local $to i32) (local $to_len i32) (local $amount f64) (local $caller_len i32) (local $id i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $read_str)
+    (local.set $to_len)
+    (local.set $to)
+    (local.set $amount (call $read_f64))
+    (if (i32.gt_u (local.get $to_len) (i32.const 64)) (then unreachable))
+    (local.set $caller_len (call $caller (i32.const 1680)))
+    (if (i32.or (i32.eqz (call $is_owner (i32.const 1680) (local.get $caller_len)))
+                (f64.le (local.get $amount) (f64.const 0)))
+      (then (return (i32.const 1))))
+    (local.set $id (call $get_u32 (call $append (i32.const 0) (i32.const 32) (i32.const 9))))
+    (if (call $store (global.get $lo) (global.get $hi) (i32.const 1280)
+          (call $proposal_key (local.get $id) (i32.const 64) (i32.const 3))
+          (local.get $to) (local.get $to_len))
+      (then (return (i32.const 1))))
+    (f64.store (i32.const 1600) (local.get $amount))
+    (if (call $store (global.get $lo) (global.get $hi) (i32.const 1280)
+          (call $proposal_key (local.get $id) (i32.const 80) (i32.const 7))
+          (i32.const 1600) (i32.const 8))
+      (then (return (i32.const 1))))
+    (if (call $set_u32 (call $append (i32.const 0) (i32.const 32) (i32.const 9))
+          (i32.add (local.get $id) (i32.const 1)))
+      (then (return (i32.const 1))))
+    (if (call $approve_proposal (local.get $id) (i32.const 1680) (local.get $caller_len))
+      (then (return (i32.const 1))))
+    (i32.store (i32.const 1600) (local.get $id))
+    (drop (call $set_return (i32.const 1600) (i32.const 4)))
+    (i32.const 0))
+
+  (func (export "approve") (param $lo i32) (param $hi i32) (result i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $approve_proposal (call $read_u32) (i32.const 1680) (call $caller (i32.const 1680))))
+
+  (func (export "execute") (param $lo i32) (param $hi i32) (result i32)
+    (local $id i32) (local $to_len i32) (local $amount f64) (local $threshold i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (local.set $id (call $read_u32))
+    (if (i32.eqz (call $is_owner (i32.const 1680) (call $caller (i32.const 1680))))
+      (then (return (i32.const 1))))
+    (local.set $to_len (call $load (global.get $lo) (global.get $hi) (i32.const 1280)
+      (call $proposal_key (local.get $id) (i32.const 64) (i32.const 3)) (i32.const 1840)))
+    (if (i32.lt_s (local.get $to_len) (i32.const 0)) (then (return (i32.const 1))))
+    (if (i32.eqz (call $has (call $proposal_key (local.get $id) (i32.const 80) (i32.const 7))))
+      (then (return (i32.const 1))))
+    (local.set $amount (f64.load (i32.const 1600)))
+    ;; A missing threshold never allows execution
+    (i32.store (i32.const 1600) (i32.const -1))
+    (drop (call $load (global.get $lo) (global.get $hi) (i32.const 1280)
+      (call $append (i32.const 0) (i32.const 16) (i32.const 9)) (i32.const 1600)))
+    (local.set $threshold (i32.load (i32.const 1600)))
+    (if (i32.lt_u (call $get_u32 (call $proposal_key (local.get $id) (i32.const 96) (i32.const 10)))
+                  (local.get $threshold))
+      (then (return (i32.const 1))))
+    (if (call $has (call $proposal_key (local.get $id) (i32.const 112) (i32.const 9)))
+      (then (return (i32.const 1))))
+    (if (f64.gt (local.get $amount) (call $self_balance (global.get $lo) (global.get $hi)))
+      (then (return (i32.const 1))))
+    (if (call $set_true (call $proposal_key (local.get $id) (i32.const 112) (i32.const 9)))
+      (then (return (i32.const 1))))
+    (if (call $transfer (global.get $lo) (global.get $hi)
+          (i32.const 1840) (local.get $to_len) (local.get $amount))
+      (then (return (i32.const 1))))
+    (i32.store (i32.const 1600) (local.get $id))
+    (drop (call $emit_event (i32.const 160) (i32.const 1) (i32.const 1600) (i32.const 4)))
+    (i32.const 0))
+)
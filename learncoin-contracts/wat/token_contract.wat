@@ -0,0 +1,170 @@
+;; Hand-written equivalent of src/bin/token.rs, used by tests when the
+;; wasm32-unknown-unknown target is not installed. Malformed input traps.
+(module
+  (import "env" "store" (func $store (param i32 i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "load" (func $load (param i32 i32 i32 i32 i32) (result i32)))
+  (import "env" "caller" (func $caller (param i32) (result i32)))
+  (import "env" "input_len" (func $input_len (result i32)))
+  (import "env" "read_input" (func $read_input (param i32) (result i32)))
+  (import "env" "set_return" (func $set_return (param i32 i32) (result i32)))
+  (import "env" "emit_event" (func $emit_event (param i32 i32 i32 i32) (result i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "supply")
+  (data (i32.const 16) "balance:")
+  (data (i32.const 32) "allowance:")
+  (data (i32.const 48) ":")
+  (data (i32.const 64) "Transfer")
+  (data (i32.const 96) "Approval")
+  ;; 256: input, 1280: key, 1600: value, 1680: caller
+  (global $lo (mut i32) (i32.const 0))
+  (global $hi (mut i32) (i32.const 0))
+  (global $pos (mut i32) (i32.const 0))
+  (global $len (mut i32) (i32.const 0))
+
+  (func $enter (param $lo i32) (param $hi i32)
+    (global.set $lo (local.get $lo))
+    (global.set $hi (local.get $hi))
+    (global.set $pos (i32.const 0))
+    (global.set $len (call $input_len))
+    (if (i32.gt_u (global.get $len) (i32.const 1024)) (then unreachable))
+    (drop (call $read_input (i32.const 256))))
+
+  (func $take (param $n i32) (result i32)
+    (local $ptr i32)
+    (if (i32.gt_u (local.get $n) (i32.sub (global.get $len) (global.get $pos))) (then unreachable))
+    (local.set $ptr (i32.add (i32.const 256) (global.get $pos)))
+    (global.set $pos (i32.add (global.get $pos) (local.get $n)))
+    (local.get $ptr))
+
+  (func $read_u32 (result i32) (i32.load (call $take (i32.const 4))))
+  (func $read_u64 (result i64) (i64.load (call $take (i32.const 8))))
+  ;; Leaves the pointer and the length of the string
+  (func $read_str (result i32 i32)
+    (local $n i32)
+    (local.set $n (call $read_u32))
+    (call $take (local.get $n))
+    (local.get $n))
+
+  (func $append (param $len i32) (param $src i32) (param $src_len i32) (result i32)
+    (if (i32.gt_u (i32.add (local.get $len) (local.get $src_len)) (i32.const 160)) (then unreachable))
+    (memory.copy (i32.add (i32.const 1280) (local.get $len)) (local.get $src) (local.get $src_len))
+    (i32.add (local.get $len) (local.get $src_len)))
+
+  (func $balance_key (param $owner i32) (param $owner_len i32) (result i32)
+    (call $append (call $append (i32.const 0) (i32.const 16) (i32.const 8))
+      (local.get $owner) (local.get $owner_len)))
+
+  (func $allowance_key (param $owner i32) (param $owner_len i32) (param $spender i32) (param $spender_len i32) (result i32)
+    (call $append
+      (call $append
+        (call $append (call $append (i32.const 0) (i32.const 32) (i32.const 10))
+          (local.get $owner) (local.get $owner_len))
+        (i32.const 48) (i32.const 1))
+      (local.get $spender) (local.get $spender_len)))
+
+  ;; Value under the key built at 1280, 0 if not set
+  (func $get_u64 (param $key_len i32) (result i64)
+    (i64.store (i32.const 1600) (i64.const 0))
+    (drop (call $load (global.get $lo) (global.get $hi) (i32.const 1280) (local.get $key_len) (i32.const 1600)))
+    (i64.load (i32.const 1600)))
+
+  (func $set_u64 (param $key_len i32) (param $value i64) (result i32)
+    (i64.store (i32.const 1600) (local.get $value))
+    (call $store (global.get $lo) (global.get $hi) (i32.const 1280) (local.get $key_len) (i32.const 1600) (i32.const 8)))
+
+  (func $balance (param $owner i32) (param $owner_len i32) (result i64)
+    (call $get_u64 (call $balance_key (local.get $owner) (local.get $owner_len))))
+
+  (func $set_balance (param $owner i32) (param $owner_len i32) (param $amount i64) (result i32)
+    (call $set_u64 (call $balance_key (local.get $owner) (local.get $owner_len)) (local.get $amount)))
+
+  (func $move_tokens (param $from i32) (param $from_len i32) (param $to i32) (param $to_len i32) (param $amount i64) (result i32)
+    (local $from_balance i64)
+    (local.set $from_balance (call $balance (local.get $from) (local.get $from_len)))
+    (if (i64.lt_u (local.get $from_balance) (local.get $amount)) (then (return (i32.const 1))))
+    (if (call $set_balance (local.get $from) (local.get $from_len)
+          (i64.sub (local.get $from_balance) (local.get $amount)))
+      (then (return (i32.const 1))))
+    (if (call $set_balance (local.get $to) (local.get $to_len)
+          (i64.add (call $balance (local.get $to) (local.get $to_len)) (local.get $amount)))
+      (then (return (i32.const 1))))
+    (i64.store (i32.const 1600) (local.get $amount))
+    (drop (call $emit_event (i32.const 64) (i32.const 1) (i32.const 1600) (i32.const 8)))
+    (i32.const 0))
+
+  (func (export "init") (param $lo i32) (param $hi i32) (result i32)
+    (local $supply i64)
+    (call $enter (local.get $lo) (local.get $hi))
+    (local.set $supply (call $read_u64))
+    (if (i32.ge_s (call $load (local.get $lo) (local.get $hi) (i32.const 0) (i32.const 6) (i32.const 1600))
+                  (i32.const 0))
+      (then (return (i32.const 1))))
+    (i64.store (i32.const 1600) (local.get $supply))
+    (if (call $store (local.get $lo) (local.get $hi) (i32.const 0) (i32.const 6) (i32.const 1600) (i32.const 8))
+      (then (return (i32.const 1))))
+    (call $set_balance (i32.const 1680) (call $caller (i32.const 1680)) (local.get $supply)))
+
+  (func (export "transfer") (param $lo i32) (param $hi i32) (result i32)
+    (local $to i32) (local $to_len i32) (local $amount i64)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $read_str)
+    (local.set $to_len)
+    (local.set $to)
+    (local.set $amount (call $read_u64))
+    (call $move_tokens (i32.const 1680) (call $caller (i32.const 1680))
+      (local.get $to) (local.get $to_len) (local.get $amount)))
+
+  (func (export "approve") (param $lo i32) (param $hi i32) (result i32)
+    (local $spender i32) (local $spender_len i32) (local $amount i64)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $read_str)
+    (local.set $spender_len)
+    (local.set $spender)
+    (local.set $amount (call $read_u64))
+    (if (call $set_u64
+          (call $allowance_key (i32.const 1680) (call $caller (i32.const 1680))
+            (local.get $spender) (local.get $spender_len))
+          (local.get $amount))
+      (then (return (i32.const 1))))
+    (i64.store (i32.const 1600) (local.get $amount))
+    (drop (call $emit_event (i32.const 96) (i32.const 1) (i32.const 1600) (i32.const 8)))
+    (i32.const 0))
+
+  (func (export "transfer_from") (param $lo i32) (param $hi i32) (result i32)
+    (local $from i32) (local $from_len i32) (local $to i32) (local $to_len i32)
+    (local $amount i64) (local $key_len i32) (local $allowance i64)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $read_str)
+    (local.set $from_len)
+    (local.set $from)
+    (call $read_str)
+    (local.set $to_len)
+    (local.set $to)
+    (local.set $amount (call $read_u64))
+    (local.set $key_len
+      (call $allowance_key (local.get $from) (local.get $from_len)
+        (i32.const 1680) (call $caller (i32.const 1680))))
+    (local.set $allowance (call $get_u64 (local.get $key_len)))
+    (if (i64.lt_u (local.get $allowance) (local.get $amount)) (then (return (i32.const 1))))
+    (if (call $set_u64 (local.get $key_len) (i64.sub (local.get $allowance) (local.get $amount)))
+      (then (return (i32.const 1))))
+    (call $move_tokens (local.get $from) (local.get $from_len)
+      (local.get $to) (local.get $to_len) (local.get $amount)))
+
+  (func (export "balance_of") (param $lo i32) (param $hi i32) (result i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (i64.store (i32.const 1600) (call $balance (call $read_str)))
+    (drop (call $set_return (i32.const 1600) (i32.const 8)))
+    (i32.const 0))
+
+  (func (export "allowance") (param $lo i32) (param $hi i32) (result i32)
+    (local $owner i32) (local $owner_len i32)
+    (call $enter (local.get $lo) (local.get $hi))
+    (call $read_str)
+    (local.set $owner_len)
+    (local.set $owner)
+    (i64.store (i32.const 1600)
+      (call $get_u64 (call $allowance_key (local.get $owner) (local.get $owner_len) (call $read_str))))
+    (drop (call $set_return (i32.const 1600) (i32.const 8)))
+    (i32.const 0))
+)
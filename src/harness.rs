@@ -0,0 +1,294 @@
+//! In-process harness for contract tests.
+//!
+//! Contracts are built from `learncoin-contracts` for `wasm32-unknown-unknown`
+//! on first use. When the target is not installed the hand-written `.wat`
+//! equivalents from `learncoin-contracts/wat` are used instead, which is
+//! logged to stderr.
+
+use bchain::{
+    clock::TickingClock,
+    message::{BlockchainFacade, Message, MessageType},
+    primitives::{Receipt, Transaction, TransactionType, Vm},
+    Blockchain, GENESIS,
+};
+use std::{
+    path::PathBuf,
+    process::Command,
+    sync::{Arc, OnceLock},
+};
+
+/// Kept apart from the workspace target, which is locked while tests run
+const CONTRACTS_TARGET_DIR: &str = "target/contracts";
+
+const FEE: f64 = 1.0;
+
+/// Where the code of a contract comes from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    /// `learncoin-contracts` built for `wasm32-unknown-unknown`
+    Rust,
+    /// Hand-written equivalent from `learncoin-contracts/wat`
+    Wat,
+}
+
+fn build_contracts() -> bool {
+    static BUILT: OnceLock<bool> = OnceLock::new();
+    *BUILT.get_or_init(|| {
+        let output = Command::new(env!("CARGO"))
+            .current_dir(env!("CARGO_MANIFEST_DIR"))
+            .args([
+                "build",
+                "--release",
+                "--target",
+                "wasm32-unknown-unknown",
+                "-p",
+                "learncoin-contracts",
+                "--bins",
+                "--target-dir",
+                CONTRACTS_TARGET_DIR,
+            ])
+            .output();
+        match output {
+            Ok(output) if output.status.success() => true,
+            Ok(output) => {
+                eprintln!(
+                    "learncoin-contracts did not build for wasm32-unknown-unknown, \
+                     using the .wat contracts:\n{}",
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                false
+            }
+            Err(e) => {
+                eprintln!("Could not run cargo ({}), using the .wat contracts", e);
+                false
+            }
+        }
+    })
+}
+
+fn wat_fallback(name: &str) -> &'static str {
+    match name {
+        "counter_contract" => include_str!("../learncoin-contracts/wat/counter_contract.wat"),
+        "token_contract" => include_str!("../learncoin-contracts/wat/token_contract.wat"),
+        "multisig_contract" => include_str!("../learncoin-contracts/wat/multisig_contract.wat"),
+        "escrow_contract" => include_str!("../learncoin-contracts/wat/escrow_contract.wat"),
+        _ => panic!("No contract named {}", name),
+    }
+}
+
+/// Code of the contract `name` from `source`, None for the Rust build when
+/// it is not available
+pub fn contract_code_from(name: &str, source: Source) -> Option<Vec<u8>> {
+    match source {
+        Source::Rust if build_contracts() => {
+            let path: PathBuf = [
+                env!("CARGO_MANIFEST_DIR"),
+                CONTRACTS_TARGET_DIR,
+                "wasm32-unknown-unknown/release",
+                &format!("{}.wasm", name),
+            ]
+            .iter()
+            .collect();
+            Some(std::fs::read(path).unwrap())
+        }
+        Source::Rust => None,
+        Source::Wat => Some(wat::parse_str(wat_fallback(name)).unwrap()),
+    }
+}
+
+/// Code of the contract built as the `name` binary of `learncoin-contracts`,
+/// or of its `.wat` equivalent when the build is not available
+pub fn contract_code(name: &str) -> Vec<u8> {
+    let (source, code) = contract_code_from(name, Source::Rust)
+        .map(|code| (Source::Rust, code))
+        .unwrap_or_else(|| (Source::Wat, contract_code_from(name, Source::Wat).unwrap()));
+    eprintln!("Deploying the {:?} code of {}", source, name);
+    code
+}
+
+/// Encodes call input the way `learncoin_contracts::input::Reader` decodes it
+#[derive(Default)]
+pub struct Input(Vec<u8>);
+
+impl Input {
+    pub fn str(mut self, s: &str) -> Self {
+        self.0.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        self.0.extend_from_slice(s.as_bytes());
+        self
+    }
+
+    pub fn u32(mut self, n: u32) -> Self {
+        self.0.extend_from_slice(&n.to_le_bytes());
+        self
+    }
+
+    pub fn u64(mut self, n: u64) -> Self {
+        self.0.extend_from_slice(&n.to_le_bytes());
+        self
+    }
+
+    pub fn f64(mut self, n: f64) -> Self {
+        self.0.extend_from_slice(&n.to_le_bytes());
+        self
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+/// Fresh blockchain with pre-funded accounts, every transaction is produced
+/// in its own block by `GENESIS`, in the next slot, and pays a fee of 1.0
+pub struct Fixture {
+    pub blockchain: Blockchain,
+}
+
+impl Fixture {
+    pub fn new(accounts: &[(&str, f64)]) -> Self {
+        let mut fixture = Fixture {
+            blockchain: Blockchain::with_clock(Arc::new(TickingClock::default())),
+        };
+        for (user, amount) in accounts {
+            fixture.fund(user, *amount);
+        }
+        fixture
+    }
+
+    pub fn height(&self) -> usize {
+        self.blockchain.get_height()
+    }
+
+    /// Produces a block and returns the receipt of its only transaction
+    pub fn submit(&mut self, tx: Transaction) -> Receipt {
+        self.blockchain
+            .receive(Message {
+                msg_type: MessageType::ProduceBlock(GENESIS.to_owned(), vec![tx]),
+            })
+            .unwrap();
+        self.blockchain.get_receipts(self.height()).unwrap()[0].clone()
+    }
+
+    pub fn fund(&mut self, user: &str, amount: f64) {
+        self.submit(Transaction::new(
+            GENESIS.to_owned(),
+            TransactionType::Transfer {
+                sender: GENESIS.to_owned(),
+                receiver: user.to_owned(),
+                amount,
+            },
+            0.,
+        ));
+    }
+
+    /// Produces empty blocks
+    pub fn skip_blocks(&mut self, count: usize) {
+        for _ in 0..count {
+            self.blockchain
+                .receive(Message {
+                    msg_type: MessageType::ProduceBlock(GENESIS.to_owned(), vec![]),
+                })
+                .unwrap();
+        }
+    }
+
+    /// Deploys the contract `name` and returns its address
+    pub fn deploy(&mut self, deployer: &str, name: &str) -> String {
+        self.deploy_code(deployer, contract_code(name))
+    }
+
+    pub fn deploy_code(&mut self, deployer: &str, code: Vec<u8>) -> String {
+        let receipt = self.submit(Transaction::new(
+            deployer.to_string(),
+            TransactionType::DeployContract {
+                code,
+                salt: None,
+                vm: Vm::Wasm,
+            },
+            FEE,
+        ));
+        receipt.contract_address.unwrap()
+    }
+
+    pub fn call(
+        &mut self,
+        sender: &str,
+        contract_address: &str,
+        method: &str,
+        input: Input,
+        value: f64,
+    ) -> Receipt {
+        self.submit(Transaction::new(
+            sender.to_string(),
+            TransactionType::CallContract {
+                contract_address: contract_address.to_string(),
+                value,
+                method: method.to_string(),
+                input: input.into_bytes(),
+            },
+            FEE,
+        ))
+    }
+
+    /// Read-only call, no block is produced
+    pub fn query(&mut self, contract_address: &str, method: &str, input: Input) -> Receipt {
+        self.blockchain
+            .query_contract(contract_address, method, &input.into_bytes())
+            .unwrap()
+    }
+
+    pub fn balance(&self, user: &str) -> f64 {
+        self.blockchain.get_wallet(user).unwrap().balance
+    }
+
+    #[track_caller]
+    pub fn assert_balance(&self, user: &str, expected: f64) {
+        assert_eq!(self.balance(user), expected, "balance of {}", user);
+    }
+
+    #[track_caller]
+    pub fn assert_no_wallet(&self, user: &str) {
+        assert!(
+            self.blockchain.get_wallet(user).is_err(),
+            "{} has a wallet",
+            user
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_storage(&self, contract_address: &str, key: &str, expected: &[u8]) {
+        assert_eq!(
+            self.blockchain
+                .get_storage(contract_address, key)
+                .map(Vec::as_slice),
+            Some(expected),
+            "storage key {}",
+            key
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_no_storage(&self, contract_address: &str, key: &str) {
+        assert_eq!(
+            self.blockchain.get_storage(contract_address, key),
+            None,
+            "storage key {}",
+            key
+        );
+    }
+}
+
+#[track_caller]
+pub fn assert_success(receipt: &Receipt) {
+    assert_eq!(receipt.exit_code, 0, "call failed: {:?}", receipt.error);
+}
+
+#[track_caller]
+pub fn assert_failure(receipt: &Receipt, reason: &str) {
+    assert_ne!(receipt.exit_code, 0, "call succeeded, expected: {}", reason);
+}
+
+#[track_caller]
+pub fn assert_returns(receipt: &Receipt, expected: &[u8]) {
+    assert_success(receipt);
+    assert_eq!(receipt.return_data, expected);
+}
@@ -2,114 +2,376 @@ fn main() {
     println!("Hello, world!");
 }
 
+#[cfg(test)]
+mod harness;
+
 #[cfg(test)]
 mod tests {
-    use bchain::{
-        message::BlockchainFacade,
-        primitives::{Transaction, TransactionType, Wallet},
-        Blockchain, GENESIS,
+    use bchain::message::BlockchainFacade;
+    use bchain::primitives::{Event, TxLocation};
+
+    use crate::harness::{
+        assert_failure, assert_returns, assert_success, contract_code_from, Fixture, Input, Source,
     };
 
-    fn produce_block_with_single_tx<T: BlockchainFacade>(blockchain: &mut T, tx: Transaction) {
-        let res = blockchain.receive(bchain::message::Message {
-            msg_type: bchain::message::MessageType::ProduceBlock(GENESIS.to_owned(), vec![tx]),
-        });
+    fn token_balance(fixture: &mut Fixture, token: &str, owner: &str) -> u64 {
+        let receipt = fixture.query(token, "balance_of", Input::default().str(owner));
+        assert_success(&receipt);
+        u64::from_le_bytes(receipt.return_data.try_into().unwrap())
+    }
+
+    #[test]
+    fn test_token_transfer_and_allowance() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0), ("Carol", 50.0)]);
+        let token = fixture.deploy("Alice", "token_contract");
+
+        // The value pays the storage deposit of balances and allowances
+        let receipt = fixture.call("Alice", &token, "init", Input::default().u64(1000), 5.0);
+        assert_success(&receipt);
+        fixture.assert_storage(&token, "balance:Alice", &1000u64.to_le_bytes());
+        let receipt = fixture.call("Alice", &token, "init", Input::default().u64(1000), 0.0);
+        assert_failure(&receipt, "init can only run once");
 
-        assert!(res.is_ok());
+        let transfer = Input::default().str("Bob").u64(300);
+        let receipt = fixture.call("Alice", &token, "transfer", transfer, 0.0);
+        assert_success(&receipt);
+        assert_eq!(receipt.events.len(), 1);
+        let transfer = Input::default().str("Bob").u64(701);
+        let receipt = fixture.call("Alice", &token, "transfer", transfer, 0.0);
+        assert_failure(&receipt, "cannot transfer more than the balance");
+        assert_eq!(token_balance(&mut fixture, &token, "Alice"), 700);
+        assert_eq!(token_balance(&mut fixture, &token, "Bob"), 300);
+
+        let approve = Input::default().str("Carol").u64(100);
+        let receipt = fixture.call("Alice", &token, "approve", approve, 0.0);
+        assert_success(&receipt);
+        let transfer = Input::default().str("Alice").str("Carol").u64(60);
+        let receipt = fixture.call("Carol", &token, "transfer_from", transfer, 0.0);
+        assert_success(&receipt);
+        let transfer = Input::default().str("Alice").str("Carol").u64(50);
+        let receipt = fixture.call("Carol", &token, "transfer_from", transfer, 0.0);
+        assert_failure(&receipt, "cannot spend more than the allowance");
+
+        let allowance = Input::default().str("Alice").str("Carol");
+        let receipt = fixture.query(&token, "allowance", allowance);
+        assert_returns(&receipt, &40u64.to_le_bytes());
+        assert_eq!(token_balance(&mut fixture, &token, "Alice"), 640);
+        assert_eq!(token_balance(&mut fixture, &token, "Carol"), 60);
     }
 
-    fn insert_wallet<T: BlockchainFacade>(blockchain: &mut T, user: &str, amount: f64) {
-        let tx = Transaction::new(
-            GENESIS.to_owned(),
-            TransactionType::Transfer {
-                sender: GENESIS.to_owned(),
-                receiver: user.to_owned(),
-                amount,
-            },
-            0.,
-        );
-        produce_block_with_single_tx(blockchain, tx);
+    #[test]
+    fn test_multisig_requires_threshold_approvals() {
+        let mut fixture = Fixture::new(&[
+            ("Alice", 500.0),
+            ("Bob", 50.0),
+            ("Carol", 50.0),
+            ("Eve", 50.0),
+        ]);
+        let multisig = fixture.deploy("Alice", "multisig_contract");
+
+        let owners = Input::default()
+            .u32(2)
+            .u32(3)
+            .str("Alice")
+            .str("Bob")
+            .str("Carol");
+        let receipt = fixture.call("Alice", &multisig, "init", owners, 100.0);
+        assert_success(&receipt);
+        fixture.assert_storage(&multisig, "owner:Bob", &[1]);
+        fixture.assert_no_storage(&multisig, "owner:Dave");
+
+        let propose = Input::default().str("Dave").f64(30.0);
+        let receipt = fixture.call("Eve", &multisig, "propose", propose, 0.0);
+        assert_failure(&receipt, "only owners propose");
+        let propose = Input::default().str("Dave").f64(30.0);
+        let receipt = fixture.call("Carol", &multisig, "propose", propose, 0.0);
+        assert_success(&receipt);
+        let id = u32::from_le_bytes(receipt.return_data.try_into().unwrap());
+
+        let proposal = || Input::default().u32(id);
+        let receipt = fixture.call("Carol", &multisig, "execute", proposal(), 0.0);
+        assert_failure(&receipt, "one approval is below the threshold");
+        fixture.assert_no_wallet("Dave");
+
+        let receipt = fixture.call("Carol", &multisig, "approve", proposal(), 0.0);
+        assert_failure(&receipt, "an owner approves only once");
+        let receipt = fixture.call("Bob", &multisig, "approve", proposal(), 0.0);
+        assert_success(&receipt);
+
+        let receipt = fixture.call("Alice", &multisig, "execute", proposal(), 0.0);
+        assert_success(&receipt);
+        fixture.assert_balance("Dave", 30.0);
+
+        let receipt = fixture.call("Alice", &multisig, "execute", proposal(), 0.0);
+        assert_failure(&receipt, "a proposal is executed once");
+        fixture.assert_balance("Dave", 30.0);
     }
 
     #[test]
-    fn test_wasm_simple_contract() {
-        let mut blockchain = Blockchain::new();
+    fn test_multisig_rejects_proposal_above_balance() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0), ("Bob", 50.0)]);
+        let multisig = fixture.deploy("Alice", "multisig_contract");
+        let owners = Input::default().u32(2).u32(2).str("Alice").str("Bob");
+        let receipt = fixture.call("Alice", &multisig, "init", owners, 100.0);
+        assert_success(&receipt);
 
-        insert_wallet(&mut blockchain, "Alice", 500.0);
+        let propose = Input::default().str("Dave").f64(300.0);
+        let receipt = fixture.call("Alice", &multisig, "propose", propose, 0.0);
+        assert_success(&receipt);
+        let id = u32::from_le_bytes(receipt.return_data.try_into().unwrap());
+        let proposal = || Input::default().u32(id);
+        let receipt = fixture.call("Bob", &multisig, "approve", proposal(), 0.0);
+        assert_success(&receipt);
 
-        // Load the Wasm contract bytecode, that is very un-unittest like :D
-        let wasm_bytes =
-            std::fs::read("target/wasm32-unknown-unknown/release/counter_contract.wasm").unwrap();
+        let balance = fixture.balance(&multisig);
+        let receipt = fixture.call("Alice", &multisig, "execute", proposal(), 0.0);
+        assert_failure(&receipt, "the balance does not cover the proposal");
+        fixture.assert_no_wallet("Dave");
+        fixture.assert_balance(&multisig, balance);
 
-        // Deploy the contract
-        let tx1 = Transaction::new(
-            "Alice".to_string(),
-            TransactionType::DeployContract { code: wasm_bytes },
-            1.0,
-        );
-        produce_block_with_single_tx(&mut blockchain, tx1);
-
-        // Call the contract
-        let tx2 = Transaction::new(
-            "Alice".to_string(),
-            TransactionType::CallContract {
-                contract_address: "contract_0".to_string(),
-            },
-            1.0,
+        // Funds added with the call cover it, and the proposal is still pending
+        let receipt = fixture.call("Alice", &multisig, "execute", proposal(), 300.0);
+        assert_success(&receipt);
+        fixture.assert_balance("Dave", 300.0);
+        // The payout shows in the history of the recipient
+        assert_eq!(receipt.paid_accounts, vec!["Dave".to_string()]);
+        let execution = TxLocation {
+            height: fixture.height(),
+            index: 0,
+        };
+        assert_eq!(
+            fixture.blockchain.get_account_history("Dave", 0, 10),
+            [execution]
         );
-        produce_block_with_single_tx(&mut blockchain, tx2);
     }
 
     #[test]
-    fn test_contract_execution() {
-        let mut blockchain = Blockchain::new();
+    fn test_escrow_releases_after_height() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0), ("Bob", 10.0)]);
+        let escrow = fixture.deploy("Alice", "escrow_contract");
 
-        insert_wallet(&mut blockchain, "Alice", 500.0);
+        let release_height = fixture.height() as u64 + 4;
+        let init = Input::default().str("Bob").u64(release_height);
+        let receipt = fixture.call("Alice", &escrow, "init", init, 50.0);
+        assert_success(&receipt);
 
-        let wasm_bytes =
-            std::fs::read("target/wasm32-unknown-unknown/release/counter_contract.wasm").unwrap();
-        // Deploy the contract
-        let tx1 = Transaction::new(
-            "Alice".to_string(),
-            TransactionType::DeployContract { code: wasm_bytes },
-            1.0,
-        );
-        produce_block_with_single_tx(&mut blockchain, tx1);
+        let receipt = fixture.call("Bob", &escrow, "release", Input::default(), 0.0);
+        assert_failure(&receipt, "locked until the release height");
+        let receipt = fixture.call("Bob", &escrow, "cancel", Input::default(), 0.0);
+        assert_failure(&receipt, "only the depositor can cancel");
+
+        fixture.skip_blocks(1);
+        let escrowed = fixture.balance(&escrow);
+        let bob_balance = fixture.balance("Bob");
+        let receipt = fixture.call("Bob", &escrow, "release", Input::default(), 0.0);
+        assert_success(&receipt);
+        fixture.assert_balance(&escrow, 0.0);
+        // The escrow minus the fee and the deposit for the settled flag
+        let bob_gain = fixture.balance("Bob") - bob_balance;
+        assert!(bob_gain > escrowed - 1.1 && bob_gain < escrowed - 1.0);
+        assert_eq!(receipt.paid_accounts, vec!["Bob".to_string()]);
+
+        let receipt = fixture.call("Alice", &escrow, "cancel", Input::default(), 0.0);
+        assert_failure(&receipt, "the escrow is settled");
+    }
+
+    #[test]
+    fn test_escrow_cancelled_before_height() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0)]);
+        let escrow = fixture.deploy("Alice", "escrow_contract");
+
+        let release_height = fixture.height() as u64 + 10;
+        let init = Input::default().str("Bob").u64(release_height);
+        let receipt = fixture.call("Alice", &escrow, "init", init, 50.0);
+        assert_success(&receipt);
+
+        let receipt = fixture.call("Alice", &escrow, "cancel", Input::default(), 0.0);
+        assert_success(&receipt);
+        fixture.assert_balance(&escrow, 0.0);
+        fixture.assert_no_wallet("Bob");
+        // Only the fees of the deployment and both calls and the deposit for
+        // 58 bytes of storage are gone
+        let alice_balance = fixture.balance("Alice");
+        assert!((alice_balance - (500.0 - 3.0 - 0.58)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wasm_simple_contract() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0)]);
+        let contract_address = fixture.deploy("Alice", "counter_contract");
+
+        // The value covers the storage deposit of the counter
+        let receipt = fixture.call("Alice", &contract_address, "execute", Input::default(), 1.0);
+        assert_success(&receipt);
+        fixture.assert_storage(&contract_address, "counter", &1i32.to_le_bytes());
+    }
+
+    #[test]
+    fn test_contract_execution() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0)]);
+        let contract_address = fixture.deploy("Alice", "counter_contract");
 
         // Call the contract multiple times to increment the counter
         for i in 1..=5 {
-            let tx = Transaction::new(
-                "Alice".to_string(),
-                TransactionType::CallContract {
-                    contract_address: "contract_0".to_string(),
-                },
-                1.0,
-            );
-            produce_block_with_single_tx(&mut blockchain, tx);
-            println!(
-                "After block {}:\nAlice={:#?}\nBob={:#?}",
-                i,
-                blockchain.get_wallet("Alice"),
-                blockchain.get_wallet("Bob")
+            // The first call covers the storage deposit of the counter, the
+            // third the stake the contract puts from its own balance
+            let value = match i {
+                1 => 1.0,
+                3 => 10.0,
+                _ => 0.0,
+            };
+            let receipt = fixture.call(
+                "Alice",
+                &contract_address,
+                "execute",
+                Input::default(),
+                value,
             );
+            assert_success(&receipt);
         }
 
         // Verify the results
-        let alice_wallet = blockchain.get_wallet("Alice").unwrap();
-        let bob_wallet = blockchain.get_wallet("Bob").unwrap();
+        let alice_wallet = fixture.blockchain.get_wallet("Alice").unwrap();
+        let contract_wallet = fixture.blockchain.get_wallet(&contract_address).unwrap();
 
         // After 5 calls:
         // - Counter should be 5
-        // - Transfer: 50 tokens from Alice to Bob (happens once, since balance drops below 100 after the first call)
-        // - Fees: 5 blocks * 1.0 = 5.0
-        // - Staking: After counter > 2 (call 3), stake 10 tokens
-        // - Unstaking: After counter > 4 (call 5), unstake 5 tokens
-        assert_eq!(alice_wallet.balance, 435.0); // 500 - 50 (transfer) - 5 (fees) - 10 (stake) + 0 (unstake not yet processed)
-        assert_eq!(alice_wallet.staked, 5.0); // 10 (stake) - 5 (unstake)
-        assert!(!alice_wallet.pending_unstakes.is_empty());
-        let alice_unstake = alice_wallet.pending_unstakes.front().unwrap();
-        assert_eq!(alice_unstake.amount, 5.0);
-        assert_eq!(alice_unstake.effective_epoch, 2); // Current epoch 5 + delay 2
-        assert_eq!(bob_wallet.balance, 50.0);
+        // - Fees: 6 blocks (the deployment and 5 calls) * 1.0 = 6.0
+        // - Storage deposit: 1.0 sent to the contract with the first call
+        // - Staking: After counter > 2 (call 3), the contract stakes 10 tokens sent with the call
+        // - Unstaking: After counter > 4 (call 5), the contract unstakes 5 tokens
+        fixture.assert_storage(&contract_address, "counter", &5i32.to_le_bytes());
+        assert_eq!(alice_wallet.balance, 483.0); // 500 - 6 (fees) - 1 (deposit) - 10 (sent to stake)
+        assert_eq!(alice_wallet.staked, 0.0);
+        assert_eq!(contract_wallet.staked, 5.0); // 10 (stake) - 5 (unstake)
+        assert!(!contract_wallet.pending_unstakes.is_empty());
+        let contract_unstake = contract_wallet.pending_unstakes.front().unwrap();
+        assert_eq!(contract_unstake.amount, 5.0);
+        assert_eq!(contract_unstake.effective_epoch, 2); // Current epoch 5 + delay 2
+    }
+
+    #[test]
+    fn test_contract_cannot_stake_the_callers_funds() {
+        let mut fixture = Fixture::new(&[("Alice", 500.0)]);
+        let contract_address = fixture.deploy("Alice", "counter_contract");
+        let execute = |fixture: &mut Fixture, value: f64| {
+            fixture.call(
+                "Alice",
+                &contract_address,
+                "execute",
+                Input::default(),
+                value,
+            )
+        };
+        assert_success(&execute(&mut fixture, 1.0));
+        assert_success(&execute(&mut fixture, 0.0));
+
+        // The third call stakes 10 tokens, which the contract does not have
+        let balance = fixture.balance("Alice");
+        let receipt = execute(&mut fixture, 0.0);
+        assert_failure(&receipt, "the contract's balance does not cover the stake");
+        let alice_wallet = fixture.blockchain.get_wallet("Alice").unwrap();
+        assert_eq!(alice_wallet.balance, balance - 1.0); // Only the fee
+        assert_eq!(alice_wallet.staked, 0.0);
+        assert_eq!(
+            fixture
+                .blockchain
+                .get_wallet(&contract_address)
+                .unwrap()
+                .staked,
+            0.0
+        );
+    }
+
+    /// Exit code, return data and events of a call
+    type Outcome = (i32, Vec<u8>, Vec<Event>);
+
+    /// Outcomes of each call of a run through all the contracts, followed by
+    /// the balances it leaves
+    fn run_contracts(source: Source) -> (Vec<Outcome>, Vec<f64>) {
+        let mut fixture = Fixture::new(&[("Alice", 500.0), ("Bob", 50.0)]);
+        let mut deploy = |name| {
+            let code = contract_code_from(name, source).unwrap();
+            fixture.deploy_code("Alice", code)
+        };
+        let counter = deploy("counter_contract");
+        let token = deploy("token_contract");
+        let multisig = deploy("multisig_contract");
+        let escrow = deploy("escrow_contract");
+
+        let owners = Input::default().u32(1).u32(2).str("Alice").str("Bob");
+        let calls = [
+            ("Alice", &counter, "execute", Input::default(), 1.0),
+            ("Alice", &counter, "execute", Input::default(), 0.0),
+            ("Alice", &token, "init", Input::default().u64(1000), 5.0),
+            (
+                "Alice",
+                &token,
+                "transfer",
+                Input::default().str("Bob").u64(300),
+                0.0,
+            ),
+            (
+                "Alice",
+                &token,
+                "transfer",
+                Input::default().str("Bob").u64(701),
+                0.0,
+            ),
+            (
+                "Bob",
+                &token,
+                "balance_of",
+                Input::default().str("Bob"),
+                0.0,
+            ),
+            ("Alice", &multisig, "init", owners, 100.0),
+            (
+                "Bob",
+                &multisig,
+                "propose",
+                Input::default().str("Dave").f64(30.0),
+                0.0,
+            ),
+            (
+                "Bob",
+                &multisig,
+                "propose",
+                Input::default().str("Dave").f64(300.0),
+                0.0,
+            ),
+            ("Alice", &multisig, "execute", Input::default().u32(0), 0.0),
+            ("Alice", &multisig, "execute", Input::default().u32(1), 0.0),
+            (
+                "Alice",
+                &escrow,
+                "init",
+                Input::default().str("Bob").u64(0),
+                50.0,
+            ),
+            ("Bob", &escrow, "cancel", Input::default(), 0.0),
+            ("Bob", &escrow, "release", Input::default(), 0.0),
+        ];
+        let receipts = calls
+            .into_iter()
+            .map(|(sender, contract, method, input, value)| {
+                let receipt = fixture.call(sender, contract, method, input, value);
+                (receipt.exit_code, receipt.return_data, receipt.events)
+            })
+            .collect();
+        let balances = ["Alice", "Bob", "Dave", &counter, &token, &multisig, &escrow]
+            .iter()
+            .map(|user| fixture.balance(user))
+            .collect();
+        (receipts, balances)
+    }
+
+    #[test]
+    fn test_rust_contracts_behave_like_wat_equivalents() {
+        if contract_code_from("counter_contract", Source::Rust).is_none() {
+            eprintln!("Skipped, the Rust contracts did not build");
+            return;
+        }
+        assert_eq!(run_contracts(Source::Rust), run_contracts(Source::Wat));
     }
 }